@@ -0,0 +1,274 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::grid::{Coord, Direction, Grid, GridElem};
+use crate::tree::Tree;
+
+/// Direction of a single step from `here` to the adjacent location `there`.
+pub fn direction_between(here: Coord, there: Coord) -> Direction {
+    match (there.0 - here.0, there.1 - here.1) {
+        (1, 0) => Direction::East,
+        (-1, 0) => Direction::West,
+        (0, -1) => Direction::North,
+        (0, 1) => Direction::South,
+        _ => {
+            panic!("Invalid coords: here={:?}, there={:?}", here, there);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SpanningTreeState {
+    /// Parent of coord in the shortest path back to the root.
+    parent: Option<Coord>,
+    /// Depth of coordinate in the spanning tree.
+    depth: u64,
+}
+
+/// Maps out an unknown maze by steering something that can only try to step
+/// in a direction and report what it found there.
+///
+/// The explorer starts at `(0, 0)`. Locations are explored in breadth-first
+/// order, walking back through already-explored locations to reach each new
+/// one. Tiles equal to `T::default()` are treated as unexplored, so the probe
+/// should never report them.
+#[derive(Debug)]
+pub struct MazeExplorer<T: GridElem + PartialEq> {
+    /// Grid of currently known tile states.
+    grid: Grid<T>,
+
+    /// Queue of unexplored locations that are known to be reachable.
+    frontier: VecDeque<Coord>,
+
+    /// Map from explored location to parent and depth from start.
+    spanning_tree: HashMap<Coord, SpanningTreeState>,
+
+    /// Current location.
+    location: Coord,
+
+    /// Can the explorer move onto a tile?
+    passable: fn(T) -> bool,
+}
+
+impl<T: GridElem + PartialEq> MazeExplorer<T> {
+    pub fn new(start: T, passable: fn(T) -> bool) -> MazeExplorer<T> {
+        let mut spanning_tree = HashMap::new();
+        spanning_tree.insert(
+            (0, 0),
+            SpanningTreeState {
+                parent: None,
+                depth: 0,
+            },
+        );
+
+        let mut explorer = MazeExplorer {
+            grid: Grid::empty(),
+            frontier: VecDeque::new(),
+            spanning_tree,
+            location: (0, 0),
+            passable,
+        };
+
+        explorer.mark_explored((0, 0), start);
+
+        explorer
+    }
+
+    /// Grid of currently known tile states.
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+
+    /// Number of steps from the start to `loc`, if it's been reached.
+    pub fn depth(&self, loc: &Coord) -> Option<u64> {
+        self.spanning_tree.get(loc).map(|state| state.depth)
+    }
+
+    /// Compute the shortest known path from the current location to goal.
+    pub fn path_to(&self, goal: Coord) -> Vec<Coord> {
+        self.shortest_path(self.location, goal)
+    }
+
+    /// Explore every reachable location.
+    ///
+    /// `probe` is called with the direction to move, and should return the
+    /// tile in that direction. If the tile is passable, the explorer assumes
+    /// that it moved there.
+    pub fn explore<F, E>(&mut self, probe: F) -> Result<(), E>
+    where
+        F: FnMut(Direction) -> Result<T, E>,
+    {
+        self.explore_until(probe, |_, _| false)?;
+        Ok(())
+    }
+
+    /// Like `explore`, but stop as soon as `done` returns true for a newly
+    /// probed location, and return that location.
+    ///
+    /// Returns None if every reachable location was explored without `done`
+    /// returning true.
+    pub fn explore_until<F, D, E>(&mut self, mut probe: F, mut done: D) -> Result<Option<Coord>, E>
+    where
+        F: FnMut(Direction) -> Result<T, E>,
+        D: FnMut(Coord, T) -> bool,
+    {
+        while let Some(goal) = self.frontier.pop_front() {
+            if self.grid.get(&goal) != T::default() {
+                continue;
+            }
+
+            let mut plan: VecDeque<Coord> = self.path_to(goal).into_iter().collect();
+            plan.pop_front(); // Path contains current location. Remove it.
+
+            for next in plan {
+                let tile = probe(direction_between(self.location, next))?;
+                self.mark_explored(next, tile);
+                if (self.passable)(tile) {
+                    self.location = next;
+                }
+                if next == goal && done(next, tile) {
+                    return Ok(Some(next));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn mark_explored(&mut self, loc: Coord, tile: T) {
+        if self.grid.get(&loc) != T::default() {
+            return;
+        }
+
+        self.grid.set(loc, tile);
+        if !(self.passable)(tile) {
+            return;
+        }
+
+        let depth = self
+            .depth(&loc)
+            .unwrap_or_else(|| panic!("Failed to get spanning tree info for {:?}", loc));
+
+        for (coord, content) in self.grid.neighbors(&loc) {
+            if content != T::default() || self.spanning_tree.contains_key(&coord) {
+                continue; // Already explored or queued.
+            }
+            self.spanning_tree.insert(
+                coord,
+                SpanningTreeState {
+                    parent: Some(loc),
+                    depth: depth + 1,
+                },
+            );
+            self.frontier.push_back(coord);
+        }
+    }
+}
+
+impl<T: GridElem + PartialEq> Tree<Coord> for MazeExplorer<T> {
+    fn parent(&self, node: Coord) -> Option<Coord> {
+        self.spanning_tree.get(&node).and_then(|state| state.parent)
+    }
+}
+
+mod tests {
+    /// Explore `maze`, starting from the location marked `D`.
+    #[allow(dead_code)]
+    fn explore_maze(maze: &str) -> super::MazeExplorer<char> {
+        use super::MazeExplorer;
+        use crate::grid::{Coord, Grid};
+
+        let maze: Grid<char> = Grid::from_str_with(maze.trim(), |c| c).unwrap();
+        let start = maze
+            .iter()
+            .find(|&(_, &c)| c == 'D')
+            .map(|(&c, _)| c)
+            .unwrap();
+
+        // Explorer coordinates are relative to the start.
+        let mut location: Coord = (0, 0);
+        let mut explorer = MazeExplorer::new('D', |c| c != '#');
+        let probe = |direction| -> Result<char, ()> {
+            let next = location + direction;
+            let tile = maze.get(&(start.0 + next.0, start.1 + next.1));
+            if tile != '#' {
+                location = next;
+            }
+            Ok(tile)
+        };
+        explorer.explore(probe).unwrap();
+        explorer
+    }
+
+    #[test]
+    fn test_explores_every_reachable_cell() {
+        use crate::grid::Coord;
+        use std::collections::HashSet;
+
+        // The cells on the right are sealed off from the start.
+        let explorer = explore_maze(
+            "
+#########
+#D..#.#x#
+#.#.#.###
+#...a.#y#
+#########",
+        );
+
+        let open: HashSet<Coord> = explorer
+            .grid()
+            .iter()
+            .filter(|&(_, &c)| c != '#')
+            .map(|(&coord, _)| coord)
+            .collect();
+
+        let expected: HashSet<Coord> = vec![
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (2, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (4, 2),
+            (4, 1),
+            (4, 0),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(open, expected);
+
+        assert_eq!(explorer.grid().get(&(3, 2)), 'a');
+        assert_eq!(explorer.depth(&(4, 0)), Some(8));
+        assert_eq!(explorer.depth(&(6, 0)), None);
+    }
+
+    #[test]
+    fn test_explore_until() {
+        use super::MazeExplorer;
+
+        let mut explorer = MazeExplorer::new('.', |c| c != '#');
+
+        // An endless corridor heading east.
+        let mut x = 0;
+        let probe = |direction| -> Result<char, ()> {
+            use crate::grid::Direction;
+            match direction {
+                Direction::East => {
+                    x += 1;
+                    Ok(if x == 5 { '*' } else { '.' })
+                }
+                Direction::West if x > 0 => {
+                    x -= 1;
+                    Ok('.')
+                }
+                _ => Ok('#'),
+            }
+        };
+
+        let found = explorer.explore_until(probe, |_, c| c == '*').unwrap();
+        assert_eq!(found, Some((5, 0)));
+        assert_eq!(explorer.depth(&(5, 0)), Some(5));
+    }
+}
@@ -1,35 +1,173 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 
-struct TreeNode<T> {
-    node: T,
-    children: Vec<TreeNode>,
-}
-
-trait Graph<'a, T: Hash + Eq> {
-    fn edges(&self, node: &T) -> Vec<&'a T>;
-
-    fn spanning_tree(&self, root: &'a T) -> SpanningTree<&'a T> {
-        let mut out = TreeNode {
-            node: root,
-            children: vec![],
-        };
+/// Trait for implementing graph-traversal algorithms on any structure
+/// providing an edge relation.
+#[allow(dead_code)]
+pub trait Graph<T: Clone + Eq + Hash> {
+    /// Get the nodes reachable from `node` by a single edge.
+    fn edges(&self, node: &T) -> Vec<T>;
 
-        // let mut seen = HashMap::new();
-        // tree.insert(root, &mut out);
+    /// Build a spanning tree of the nodes reachable from `root` using
+    /// breadth-first search.
+    ///
+    /// Returns a map from each reachable node to its parent in the tree. The
+    /// root's parent is None. Following parents from any node gives a
+    /// shortest path back to the root.
+    fn bfs_spanning_tree(&self, root: T) -> HashMap<T, Option<T>> {
+        let mut parents: HashMap<T, Option<T>> = HashMap::new();
+        parents.insert(root.clone(), None);
 
         let mut queue = VecDeque::from(vec![root]);
 
         while let Some(parent) = queue.pop_front() {
-            for child in self.edges(parent) {
-                if seen.contains_key(child) {
+            for child in self.edges(&parent) {
+                if parents.contains_key(&child) {
                     continue;
                 }
 
-                seen.get(parent).unwrap().children.push(child)
+                parents.insert(child.clone(), Some(parent.clone()));
+                queue.push_back(child);
+            }
+        }
+
+        parents
+    }
+
+    /// Order the nodes reachable from `roots` so that each node comes before
+    /// every node it has an edge to.
+    ///
+    /// Returns Err with a node on a cycle if there's no such order.
+    fn topological_order(&self, roots: &[T]) -> Result<Vec<T>, T> {
+        let mut finished: HashSet<T> = HashSet::new();
+        // Nodes on the current depth-first path.
+        let mut open: HashSet<T> = HashSet::new();
+        let mut postorder: Vec<T> = vec![];
+
+        // Each node is pushed once to be expanded, then again to be finished
+        // after all of its descendants.
+        let mut stack: Vec<(T, bool)> = roots.iter().rev().map(|r| (r.clone(), false)).collect();
 
-                tree.insert(child, TreeNode {
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                open.remove(&node);
+                finished.insert(node.clone());
+                postorder.push(node);
+                continue;
+            }
+
+            if finished.contains(&node) {
+                continue;
+            }
+            if !open.insert(node.clone()) {
+                return Err(node);
             }
+
+            stack.push((node.clone(), true));
+            for child in self.edges(&node).into_iter().rev() {
+                if !finished.contains(&child) {
+                    stack.push((child, false));
+                }
+            }
+        }
+
+        postorder.reverse();
+        Ok(postorder)
+    }
+}
+
+/// A directed graph stored as a map from each node to its outgoing edges.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct AdjacencyList<T: Clone + Eq + Hash> {
+    edges: HashMap<T, Vec<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: Clone + Eq + Hash> AdjacencyList<T> {
+    pub fn new() -> AdjacencyList<T> {
+        AdjacencyList {
+            edges: HashMap::new(),
+        }
+    }
+
+    /// Add an edge from `from` to `to`.
+    pub fn add_edge(&mut self, from: T, to: T) {
+        self.edges.entry(from).or_default().push(to);
+    }
+
+    /// Add edges in both directions between `a` and `b`.
+    pub fn add_undirected_edge(&mut self, a: T, b: T) {
+        self.add_edge(a.clone(), b.clone());
+        self.add_edge(b, a);
+    }
+}
+
+impl<T: Clone + Eq + Hash> Graph<T> for AdjacencyList<T> {
+    fn edges(&self, node: &T) -> Vec<T> {
+        self.edges.get(node).cloned().unwrap_or_default()
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_bfs_spanning_tree() {
+        use super::{AdjacencyList, Graph};
+
+        // 1 - 2 - 4 - 5
+        //  \     /
+        //   - 3 -
+        //
+        // 6 - 7
+        let mut graph = AdjacencyList::new();
+        for &(a, b) in &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5), (6, 7)] {
+            graph.add_undirected_edge(a, b);
         }
+
+        let tree = graph.bfs_spanning_tree(1);
+
+        let mut reachable: Vec<i32> = tree.keys().copied().collect();
+        reachable.sort();
+        assert_eq!(reachable, vec![1, 2, 3, 4, 5]);
+
+        assert_eq!(tree[&1], None);
+        assert_eq!(tree[&2], Some(1));
+        assert_eq!(tree[&3], Some(1));
+        assert_eq!(tree[&5], Some(4));
+        // 4 is reached first through 2, since 2 is explored before 3.
+        assert_eq!(tree[&4], Some(2));
+
+        // Edges added with add_edge are one-way.
+        let mut graph = AdjacencyList::new();
+        graph.add_edge('a', 'b');
+        graph.add_edge('b', 'c');
+        assert_eq!(graph.bfs_spanning_tree('a').len(), 3);
+        assert_eq!(graph.bfs_spanning_tree('c').len(), 1);
+    }
+
+    #[test]
+    fn test_topological_order() {
+        use super::{AdjacencyList, Graph};
+
+        // a -> b -> d
+        //  \-> c -/
+        let mut graph = AdjacencyList::new();
+        for &(from, to) in &[('a', 'b'), ('a', 'c'), ('b', 'd'), ('c', 'd'), ('e', 'c')] {
+            graph.add_edge(from, to);
+        }
+
+        let order = graph.topological_order(&['a']).unwrap();
+        assert_eq!(order, vec!['a', 'c', 'b', 'd']);
+
+        let order = graph.topological_order(&['a', 'e']).unwrap();
+        assert_eq!(order.len(), 5);
+        let position = |n: char| order.iter().position(|&x| x == n).unwrap();
+        for &(from, to) in &[('a', 'b'), ('a', 'c'), ('b', 'd'), ('c', 'd'), ('e', 'c')] {
+            assert!(position(from) < position(to));
+        }
+
+        graph.add_edge('d', 'a');
+        assert!(graph.topological_order(&['a']).is_err());
+        assert!(graph.topological_order(&['e']).is_err());
     }
 }
@@ -1,5 +1,8 @@
-use std::cmp::{max, min};
-use std::collections::HashMap;
+use std::cmp::{max, min, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+
+use crate::utils::SimpleError;
 
 pub type Coord = (i64, i64);
 
@@ -7,7 +10,7 @@ pub trait GridElem: Default + Copy + Into<char> {}
 
 impl<T: Default + Copy + Into<char>> GridElem for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Grid<T: GridElem> {
     cells: HashMap<Coord, T>,
 }
@@ -21,10 +24,131 @@ impl<T: GridElem> Grid<T> {
         Self::new(HashMap::new())
     }
 
+    /// Construct a grid from a block of text, converting each character with
+    /// `f`. The character in column `i` of line `j` is stored at `(i, j)`.
+    ///
+    /// Trailing blank lines are ignored. Lines with different lengths are an
+    /// error.
+    #[allow(dead_code)]
+    pub fn from_str_with<F>(s: &str, f: F) -> Result<Grid<T>, SimpleError>
+    where
+        F: Fn(char) -> T,
+    {
+        let lines: Vec<&str> = s.trim_end_matches('\n').lines().collect();
+        let width = lines.first().map_or(0, |line| line.chars().count());
+
+        let mut cells = HashMap::new();
+        for (j, line) in lines.iter().enumerate() {
+            let length = line.chars().count();
+            if length != width {
+                return Err(SimpleError(format!(
+                    "Line {} has length {}, expected {}",
+                    j, length, width
+                )));
+            }
+
+            for (i, c) in line.chars().enumerate() {
+                cells.insert((i as i64, j as i64), f(c));
+            }
+        }
+
+        Ok(Grid::new(cells))
+    }
+
+    /// Save the grid in a text format that can be read back with `load`.
+    ///
+    /// The first line is the coordinate of the top-left corner of the
+    /// bounding box, as `x,y`. Each following line is one row of the
+    /// bounding box, drawn as in `render_exact`. An empty grid saves as an
+    /// empty string.
+    #[allow(dead_code)]
+    pub fn save(&self) -> String {
+        match self.bounds() {
+            Some(bounds) => format!("{},{}\n{}", bounds.xmin, bounds.ymin, self.render_exact()),
+            None => String::new(),
+        }
+    }
+
+    /// Load a grid written by `save`, converting each character with `f`.
+    ///
+    /// Characters that convert to the default value are left uninitialized.
+    #[allow(dead_code)]
+    pub fn load<F>(s: &str, f: F) -> Result<Grid<T>, SimpleError>
+    where
+        F: Fn(char) -> T,
+        T: PartialEq,
+    {
+        if s.is_empty() {
+            return Ok(Grid::empty());
+        }
+
+        let (header, rows) = match s.find('\n') {
+            Some(ix) => (&s[..ix], &s[ix + 1..]),
+            None => (s, ""),
+        };
+        let bad_header = || SimpleError(format!("Bad grid origin: {:?}", header));
+        let origin: Vec<i64> = header
+            .split(',')
+            .map(|part| part.trim().parse().map_err(|_| bad_header()))
+            .collect::<Result<_, _>>()?;
+        let (x0, y0) = match origin[..] {
+            [x0, y0] => (x0, y0),
+            _ => return Err(bad_header()),
+        };
+
+        let cells = Grid::from_str_with(rows, f)?
+            .cells
+            .into_iter()
+            .filter(|&(_, value)| value != T::default())
+            .map(|((x, y), value)| ((x0 + x, y0 + y), value))
+            .collect();
+
+        Ok(Grid::new(cells))
+    }
+
+    /// Construct an empty grid with space for at least `n` cells.
+    #[allow(dead_code)]
+    pub fn with_capacity(n: usize) -> Grid<T> {
+        Self::new(HashMap::with_capacity(n))
+    }
+
+    /// Iterate over all initialized cells, in arbitrary order. See
+    /// `iter_ordered` for a deterministic order.
     pub fn iter(&self) -> impl Iterator<Item = (&Coord, &T)> {
         self.cells.iter()
     }
 
+    /// Iterate over the cells in the bounding box of the initialized cells,
+    /// in row-major order.
+    ///
+    /// If `include_defaults` is true, uninitialized cells inside the bounding
+    /// box are included with the default value. Otherwise, they're skipped.
+    #[allow(dead_code)]
+    pub fn iter_ordered(&self, include_defaults: bool) -> impl Iterator<Item = (Coord, T)> + '_ {
+        self.bounds()
+            .into_iter()
+            .flat_map(|bounds| {
+                let xs = bounds.xmin..=bounds.xmax;
+                (bounds.ymin..=bounds.ymax).flat_map(move |y| xs.clone().map(move |x| (x, y)))
+            })
+            .filter_map(move |coord| match self.cells.get(&coord) {
+                Some(&value) => Some((coord, value)),
+                None if include_defaults => Some((coord, T::default())),
+                None => None,
+            })
+    }
+
+    /// Iterate over the values of all initialized cells.
+    #[allow(dead_code)]
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        self.cells.values().copied()
+    }
+
+    /// Iterate over the coordinates of all initialized cells.
+    pub fn coords(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.cells.keys().copied()
+    }
+
     pub fn neighbors(&self, c: &Coord) -> impl Iterator<Item = (Coord, T)> {
         let mut vec = Vec::with_capacity(4);
         for direction in DIRECTIONS.iter() {
@@ -34,6 +158,20 @@ impl<T: GridElem> Grid<T> {
         vec.into_iter()
     }
 
+    /// Like `neighbors`, but including diagonals.
+    ///
+    /// Neighbors are returned clockwise starting from north: N, NE, E, SE, S,
+    /// SW, W, NW.
+    #[allow(dead_code)]
+    pub fn neighbors8(&self, c: &Coord) -> impl Iterator<Item = (Coord, T)> {
+        let mut vec = Vec::with_capacity(8);
+        for (dx, dy) in OFFSETS8.iter() {
+            let coord = (c.0 + dx, c.1 + dy);
+            vec.push((coord, self.get(&coord)));
+        }
+        vec.into_iter()
+    }
+
     pub fn get(&self, coord: &Coord) -> T {
         *self.cells.get(coord).unwrap_or(&Default::default())
     }
@@ -42,29 +180,241 @@ impl<T: GridElem> Grid<T> {
         self.cells.insert(coord, value);
     }
 
-    fn bounds(&self) -> GridBounds {
+    /// Find a shortest path from `start` to `goal` using A* search with
+    /// Manhattan distance as the heuristic.
+    ///
+    /// Only cells for which `passable` returns true are entered, and the
+    /// search never leaves the bounding box of the initialized cells. The
+    /// returned path includes both `start` and `goal`. Returns None if there
+    /// is no path.
+    #[allow(dead_code)]
+    pub fn astar<F>(&self, start: Coord, goal: Coord, passable: F) -> Option<Vec<Coord>>
+    where
+        F: Fn(T) -> bool,
+    {
+        let bounds = self.bounds();
+        let heuristic = |c: Coord| ((c.0 - goal.0).abs() + (c.1 - goal.1).abs()) as u64;
+
+        let mut came_from: HashMap<Coord, Coord> = HashMap::new();
+        let mut best_cost: HashMap<Coord, u64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        best_cost.insert(start, 0);
+        queue.push(Reverse((heuristic(start), 0, start)));
+
+        while let Some(Reverse((_, cost, coord))) = queue.pop() {
+            if coord == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some(&previous) = came_from.get(&current) {
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // Skip stale queue entries.
+            if cost > best_cost[&coord] {
+                continue;
+            }
+
+            for (next, value) in self.neighbors(&coord) {
+                if !bounds.as_ref().is_some_and(|b| b.contains(next)) || !passable(value) {
+                    continue;
+                }
+
+                let next_cost = cost + 1;
+                if best_cost.get(&next).is_none_or(|&c| next_cost < c) {
+                    best_cost.insert(next, next_cost);
+                    came_from.insert(next, coord);
+                    queue.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Find every cell reachable from `start` by moving through cells for
+    /// which `passable` returns true, along with its distance from `start`.
+    ///
+    /// `start` itself is always included, with distance 0. Like `astar`, the
+    /// search never leaves the bounding box of the initialized cells.
+    pub fn flood_fill<F>(&self, start: Coord, passable: F) -> HashMap<Coord, u64>
+    where
+        F: Fn(T) -> bool,
+    {
+        let bounds = self.bounds();
+
+        let mut distances = HashMap::new();
+        distances.insert(start, 0);
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(coord) = queue.pop_front() {
+            let distance = distances[&coord];
+
+            for (next, value) in self.neighbors(&coord) {
+                let in_bounds = bounds.as_ref().is_some_and(|b| b.contains(next));
+                if distances.contains_key(&next) || !in_bounds || !passable(value) {
+                    continue;
+                }
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+
+        distances
+    }
+
+    /// Find the cheapest cost to reach every reachable cell from `start`.
+    ///
+    /// `cost` returns the cost of stepping onto a cell, or None if the cell
+    /// can't be entered. `start` itself is always included, with cost 0. Like
+    /// `flood_fill`, the search never leaves the bounding box of the
+    /// initialized cells.
+    #[allow(dead_code)]
+    pub fn dijkstra<F>(&self, start: Coord, cost: F) -> HashMap<Coord, u64>
+    where
+        F: Fn(T) -> Option<u64>,
+    {
+        let bounds = self.bounds();
+
+        let mut best_cost: HashMap<Coord, u64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        best_cost.insert(start, 0);
+        queue.push(Reverse((0, start)));
+
+        while let Some(Reverse((total, coord))) = queue.pop() {
+            // Skip stale queue entries.
+            if total > best_cost[&coord] {
+                continue;
+            }
+
+            for (next, value) in self.neighbors(&coord) {
+                if !bounds.as_ref().is_some_and(|b| b.contains(next)) {
+                    continue;
+                }
+                let step = match cost(value) {
+                    Some(step) => step,
+                    None => continue,
+                };
+
+                let next_total = total + step;
+                if best_cost.get(&next).is_none_or(|&c| next_total < c) {
+                    best_cost.insert(next, next_total);
+                    queue.push(Reverse((next_total, next)));
+                }
+            }
+        }
+
+        best_cost
+    }
+
+    /// Bounding box of the initialized cells, or None if the grid is empty.
+    fn bounds(&self) -> Option<GridBounds> {
+        let mut coords = self.cells.keys();
+        let &(x, y) = coords.next()?;
+
         let ((xmin, xmax), (ymin, ymax)) =
-            self.cells
-                .keys()
-                .fold(((0, 0), (0, 0)), |((xmin, xmax), (ymin, ymax)), &(x, y)| {
-                    ((min(x, xmin), max(x, xmax)), (min(y, ymin), max(y, ymax)))
-                });
+            coords.fold(((x, x), (y, y)), |((xmin, xmax), (ymin, ymax)), &(x, y)| {
+                ((min(x, xmin), max(x, xmax)), (min(y, ymin), max(y, ymax)))
+            });
 
-        GridBounds {
+        Some(GridBounds {
             xmin,
             xmax,
             ymin,
             ymax,
-        }
+        })
     }
 
+    /// Render the grid, framed to always include the origin, with an extra
+    /// row and column of default cells past the bottom and right edges.
+    ///
+    /// This is the framing used for the painting robot's output. See
+    /// `render_exact` for a rendering of just the initialized cells.
     pub fn render(&self) -> String {
-        let bounds = self.bounds();
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds.including((0, 0)),
+            None => GridBounds {
+                xmin: 0,
+                xmax: 0,
+                ymin: 0,
+                ymax: 0,
+            },
+        };
 
+        self.render_region(
+            bounds.xmin..=(bounds.xmax + 1),
+            bounds.ymin..=(bounds.ymax + 1),
+        )
+    }
+
+    /// Render exactly the bounding box of the initialized cells.
+    ///
+    /// An empty grid renders as an empty string.
+    #[allow(dead_code)]
+    pub fn render_exact(&self) -> String {
+        match self.bounds() {
+            Some(bounds) => {
+                self.render_region(bounds.xmin..=bounds.xmax, bounds.ymin..=bounds.ymax)
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Convert to a `DenseGrid` covering the bounding box of the initialized
+    /// cells, or None if the grid is empty.
+    #[allow(dead_code)]
+    pub fn to_dense(&self) -> Option<DenseGrid<T>> {
+        let bounds = self.bounds()?;
+        let width = (bounds.xmax - bounds.xmin + 1) as usize;
+        let height = (bounds.ymax - bounds.ymin + 1) as usize;
+
+        let mut dense = DenseGrid::new((bounds.xmin, bounds.ymin), width, height);
+        for (&coord, &value) in self.cells.iter() {
+            dense.set(coord, value);
+        }
+        Some(dense)
+    }
+
+    /// Like `render_exact`, but choose each cell's character with `f`, which
+    /// is passed the cell's coordinate and value.
+    ///
+    /// This is useful for drawing annotations, like a path or a robot's
+    /// position, on top of a grid.
+    #[allow(dead_code)]
+    pub fn render_with<F>(&self, f: F) -> String
+    where
+        F: Fn(Coord, T) -> char,
+    {
+        match self.bounds() {
+            Some(bounds) => {
+                self.render_region_with(bounds.xmin..=bounds.xmax, bounds.ymin..=bounds.ymax, f)
+            }
+            None => String::new(),
+        }
+    }
+
+    fn render_region(&self, xs: RangeInclusive<i64>, ys: RangeInclusive<i64>) -> String {
+        self.render_region_with(xs, ys, |_, value| value.into())
+    }
+
+    fn render_region_with<F>(
+        &self,
+        xs: RangeInclusive<i64>,
+        ys: RangeInclusive<i64>,
+        f: F,
+    ) -> String
+    where
+        F: Fn(Coord, T) -> char,
+    {
         let mut out = String::new();
-        for j in bounds.ymin..=(bounds.ymax + 1) {
-            for i in bounds.xmin..=(bounds.xmax + 1) {
-                out.push(self.get(&(i, j)).into());
+        for j in ys {
+            for i in xs.clone() {
+                out.push(f((i, j), self.get(&(i, j))));
             }
             out.push('\n');
         }
@@ -74,6 +424,33 @@ impl<T: GridElem> Grid<T> {
     pub fn initialized_count(&self) -> usize {
         self.cells.len()
     }
+
+    /// Find cells whose values differ between `self` and `other`.
+    ///
+    /// Checks every coordinate initialized in either grid, treating missing
+    /// cells as the default value. Returns `(coord, self_value, other_value)`
+    /// for each difference, sorted by coordinate.
+    #[allow(dead_code)]
+    pub fn diff(&self, other: &Grid<T>) -> Vec<(Coord, T, T)>
+    where
+        T: PartialEq,
+    {
+        let coords: HashSet<Coord> = self.coords().chain(other.coords()).collect();
+
+        let mut out: Vec<(Coord, T, T)> = coords
+            .into_iter()
+            .filter_map(|c| {
+                let (mine, theirs) = (self.get(&c), other.get(&c));
+                if mine != theirs {
+                    Some((c, mine, theirs))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        out.sort_by_key(|&(c, _, _)| c);
+        out
+    }
 }
 
 struct GridBounds {
@@ -83,6 +460,124 @@ struct GridBounds {
     ymax: i64,
 }
 
+impl GridBounds {
+    fn contains(&self, (x, y): Coord) -> bool {
+        self.xmin <= x && x <= self.xmax && self.ymin <= y && y <= self.ymax
+    }
+
+    /// Expand the bounds, if necessary, to include `(x, y)`.
+    fn including(self, (x, y): Coord) -> GridBounds {
+        GridBounds {
+            xmin: min(self.xmin, x),
+            xmax: max(self.xmax, x),
+            ymin: min(self.ymin, y),
+            ymax: max(self.ymax, y),
+        }
+    }
+}
+
+/// A grid with fixed bounds, stored as a flat vector in row-major order.
+///
+/// This is faster and smaller than `Grid` for regions that are mostly filled
+/// in, like images and maps. Coordinates outside the bounds read as the
+/// default value, like uninitialized cells in `Grid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenseGrid<T: GridElem> {
+    /// Coordinate of the top-left cell.
+    origin: Coord,
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+#[allow(dead_code)]
+impl<T: GridElem> DenseGrid<T> {
+    /// Construct a `width` by `height` grid of default cells, with its
+    /// top-left cell at `origin`.
+    pub fn new(origin: Coord, width: usize, height: usize) -> DenseGrid<T> {
+        DenseGrid {
+            origin,
+            width,
+            height,
+            cells: vec![T::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Is `coord` inside the grid's bounds?
+    pub fn contains(&self, coord: &Coord) -> bool {
+        self.index(coord).is_some()
+    }
+
+    pub fn get(&self, coord: &Coord) -> T {
+        match self.index(coord) {
+            Some(ix) => self.cells[ix],
+            None => Default::default(),
+        }
+    }
+
+    /// Set the value at `coord`.
+    ///
+    /// Panics if `coord` is outside the grid's bounds.
+    pub fn set(&mut self, coord: Coord, value: T) {
+        match self.index(&coord) {
+            Some(ix) => self.cells[ix] = value,
+            None => panic!("{:?} is outside of dense grid bounds", coord),
+        }
+    }
+
+    pub fn neighbors(&self, c: &Coord) -> impl Iterator<Item = (Coord, T)> {
+        let mut vec = Vec::with_capacity(4);
+        for direction in DIRECTIONS.iter() {
+            let coord = *c + *direction;
+            vec.push((coord, self.get(&coord)));
+        }
+        vec.into_iter()
+    }
+
+    /// Iterate over every coordinate in the grid and its value, in row-major
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, T)> + '_ {
+        self.cells.iter().enumerate().map(move |(ix, &value)| {
+            let x = self.origin.0 + (ix % self.width) as i64;
+            let y = self.origin.1 + (ix / self.width) as i64;
+            ((x, y), value)
+        })
+    }
+
+    /// Render every cell in the grid, one row per line.
+    pub fn render(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width.max(1)) {
+            for &value in row {
+                out.push(value.into());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Convert to a sparse `Grid` with every cell initialized.
+    pub fn to_sparse(&self) -> Grid<T> {
+        Grid::new(self.iter().collect())
+    }
+
+    fn index(&self, &(x, y): &Coord) -> Option<usize> {
+        let (dx, dy) = (x - self.origin.0, y - self.origin.1);
+        if dx < 0 || dy < 0 || dx as usize >= self.width || dy as usize >= self.height {
+            return None;
+        }
+        Some(dy as usize * self.width + dx as usize)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     North = 1,
@@ -98,6 +593,47 @@ const DIRECTIONS: [Direction; 4] = [
     Direction::West,
 ];
 
+impl Direction {
+    /// All four directions, clockwise from north.
+    #[allow(dead_code)]
+    pub fn all() -> [Direction; 4] {
+        DIRECTIONS
+    }
+
+    /// The direction pointing the other way.
+    #[allow(dead_code)]
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+        }
+    }
+
+    /// The turn that changes heading from `self` to `other`, or None if
+    /// they're already the same.
+    #[allow(dead_code)]
+    pub fn turn_to(self, other: Direction) -> Option<Turn> {
+        [Turn::CW, Turn::CCW, Turn::Around]
+            .iter()
+            .copied()
+            .find(|turn| turn.apply(self) == other)
+    }
+}
+
+/// Offsets to all eight surrounding cells, clockwise from north.
+const OFFSETS8: [(i64, i64); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
 impl Into<i64> for Direction {
     fn into(self) -> i64 {
         self as i64
@@ -117,10 +653,13 @@ impl std::ops::Add<Direction> for Coord {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Turn {
     CW,
     CCW,
+    /// A U-turn.
+    #[allow(dead_code)]
+    Around,
 }
 
 impl Turn {
@@ -135,6 +674,442 @@ impl Turn {
             (Direction::East, Turn::CCW) => Direction::North,
             (Direction::South, Turn::CCW) => Direction::East,
             (Direction::West, Turn::CCW) => Direction::South,
+
+            (_, Turn::Around) => Turn::CW.apply(Turn::CW.apply(direction)),
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_values_and_coords() {
+        use super::Grid;
+
+        let mut grid: Grid<char> = Grid::empty();
+        grid.set((0, 0), 'a');
+        grid.set((1, -1), 'b');
+        grid.set((0, 0), 'c');
+
+        assert_eq!(grid.values().count(), grid.initialized_count());
+
+        let mut values: Vec<char> = grid.values().collect();
+        values.sort();
+        assert_eq!(values, vec!['b', 'c']);
+
+        let mut coords: Vec<(i64, i64)> = grid.coords().collect();
+        coords.sort();
+        assert_eq!(coords, vec![(0, 0), (1, -1)]);
+    }
+
+    #[test]
+    fn test_clone_is_independent() {
+        use super::Grid;
+
+        let mut original: Grid<char> = Grid::with_capacity(4);
+        original.set((0, 0), 'a');
+
+        let mut copy = original.clone();
+        copy.set((0, 0), 'b');
+        copy.set((1, 0), 'c');
+
+        assert_eq!(original.get(&(0, 0)), 'a');
+        assert_eq!(original.initialized_count(), 1);
+        assert_eq!(copy.get(&(0, 0)), 'b');
+        assert_eq!(copy.initialized_count(), 2);
+    }
+
+    #[test]
+    fn test_diff() {
+        use super::Grid;
+
+        let mut left: Grid<char> = Grid::empty();
+        left.set((0, 0), 'a');
+        left.set((1, 0), 'b');
+
+        let mut right = left.clone();
+        assert_eq!(left.diff(&right), vec![]);
+
+        right.set((1, 0), 'c');
+        assert_eq!(left.diff(&right), vec![((1, 0), 'b', 'c')]);
+
+        // Cells missing from one side compare against the default value.
+        right.set((5, 5), 'd');
+        assert_eq!(
+            left.diff(&right),
+            vec![((1, 0), 'b', 'c'), ((5, 5), '\0', 'd')]
+        );
+    }
+
+    #[test]
+    fn test_astar() {
+        use super::Grid;
+
+        let mut grid: Grid<char> = Grid::empty();
+        let maze = "\
+#######
+#S..#.#
+#.#.#.#
+#.#...#
+#.###G#
+#######";
+        for (y, line) in maze.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                grid.set((x as i64, y as i64), c);
+            }
+        }
+
+        let path = grid.astar((1, 1), (5, 4), |c| c != '#').unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path.first(), Some(&(1, 1)));
+        assert_eq!(path.last(), Some(&(5, 4)));
+        assert!(path.iter().all(|c| grid.get(c) != '#'));
+
+        // Consecutive steps are adjacent.
+        for pair in path.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            assert_eq!((x1 - x2).abs() + (y1 - y2).abs(), 1);
+        }
+
+        assert_eq!(grid.astar((1, 1), (1, 1), |c| c != '#'), Some(vec![(1, 1)]));
+
+        // Wall off the goal.
+        grid.set((5, 3), '#');
+        grid.set((4, 3), '#');
+        assert_eq!(grid.astar((1, 1), (5, 4), |c| c != '#'), None);
+
+        // Unreachable even if everything is passable, since the goal is
+        // outside the grid.
+        assert_eq!(grid.astar((1, 1), (50, 50), |_| true), None);
+    }
+
+    #[test]
+    fn test_neighbors8() {
+        use super::Grid;
+
+        let mut grid: Grid<char> = Grid::empty();
+        grid.set((0, -1), 'n');
+        grid.set((1, 1), 's');
+        grid.set((-1, 0), 'w');
+
+        let neighbors: Vec<((i64, i64), char)> = grid.neighbors8(&(0, 0)).collect();
+        assert_eq!(
+            neighbors,
+            vec![
+                ((0, -1), 'n'),
+                ((1, -1), '\0'),
+                ((1, 0), '\0'),
+                ((1, 1), 's'),
+                ((0, 1), '\0'),
+                ((-1, 1), '\0'),
+                ((-1, 0), 'w'),
+                ((-1, -1), '\0'),
+            ]
+        );
+
+        // The orthogonal neighbors are unchanged.
+        assert_eq!(grid.neighbors(&(0, 0)).count(), 4);
+    }
+
+    #[test]
+    fn test_from_str_with() {
+        use super::Grid;
+
+        let text = "\
+#..#
+.##.
+";
+        let grid: Grid<char> = Grid::from_str_with(text, |c| c).unwrap();
+        assert_eq!(grid.initialized_count(), 8);
+        assert_eq!(grid.get(&(0, 0)), '#');
+        assert_eq!(grid.get(&(1, 1)), '#');
+        assert_eq!(grid.get(&(3, 1)), '.');
+
+        // render() pads the bottom and right edges with a default cell.
+        let rendered: Vec<String> = grid
+            .render()
+            .lines()
+            .map(|line| line.trim_end_matches('\0').to_string())
+            .collect();
+        assert_eq!(rendered, vec!["#..#", ".##.", ""]);
+
+        // Multiple trailing newlines are fine.
+        let grid: Grid<char> = Grid::from_str_with("ab\ncd\n\n\n", |c| c).unwrap();
+        assert_eq!(grid.initialized_count(), 4);
+
+        let err = Grid::from_str_with("abc\nde\nfgh", |c| c).unwrap_err();
+        assert_eq!(err.to_string(), "Line 1 has length 2, expected 3");
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        use super::Grid;
+
+        let text = "\
+#######
+#S..#.#
+#.#.#.#
+#.#.###
+#######";
+        let grid: Grid<char> = Grid::from_str_with(text, |c| c).unwrap();
+        let distances = grid.flood_fill((1, 1), |c| c != '#');
+
+        assert_eq!(distances.len(), 7);
+        assert_eq!(distances[&(1, 1)], 0);
+        assert_eq!(distances[&(3, 1)], 2);
+        assert_eq!(distances[&(1, 3)], 2);
+        assert_eq!(distances[&(3, 3)], 4);
+        assert_eq!(distances.values().max(), Some(&4));
+
+        // The pocket on the right is unreachable.
+        assert!(!distances.contains_key(&(5, 1)));
+        assert!(!distances.contains_key(&(5, 2)));
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        use super::Grid;
+
+        // Each digit is the cost of stepping onto that cell.
+        let text = "\
+191
+111
+1#1";
+        let grid: Grid<char> = Grid::from_str_with(text, |c| c).unwrap();
+        let cost = |c: char| c.to_digit(10).map(u64::from);
+        let costs = grid.dijkstra((0, 0), cost);
+
+        assert_eq!(costs.len(), 8);
+        assert_eq!(costs[&(0, 0)], 0);
+        assert_eq!(costs[&(1, 0)], 9);
+        assert_eq!(costs[&(2, 2)], 4);
+        assert!(!costs.contains_key(&(1, 2)));
+
+        // Going around the expensive cell is cheaper than going through it,
+        // even though it takes more steps.
+        let hops = grid.flood_fill((0, 0), |c| c != '#');
+        assert_eq!(hops[&(2, 0)], 2);
+        assert_eq!(costs[&(2, 0)], 4);
+
+        // With uniform costs, this is the same as a flood fill.
+        let uniform = grid.dijkstra((0, 0), |c| if c == '#' { None } else { Some(1) });
+        assert_eq!(uniform, hops);
+    }
+
+    #[test]
+    fn test_dense_matches_sparse() {
+        use super::{DenseGrid, Grid};
+
+        let sparse: Grid<char> = Grid::from_str_with("ab.\n.c.\nd.e", |c| c).unwrap();
+        let dense: DenseGrid<char> = sparse.to_dense().unwrap();
+
+        assert_eq!((dense.width(), dense.height()), (3, 3));
+        assert_eq!(dense.render(), sparse.render_exact());
+
+        // Including coordinates just outside the bounds.
+        for x in -1..=3 {
+            for y in -1..=3 {
+                assert_eq!(dense.get(&(x, y)), sparse.get(&(x, y)));
+
+                let dense_neighbors: Vec<_> = dense.neighbors(&(x, y)).collect();
+                let sparse_neighbors: Vec<_> = sparse.neighbors(&(x, y)).collect();
+                assert_eq!(dense_neighbors, sparse_neighbors);
+            }
+        }
+
+        let mut sparse = sparse;
+        let mut dense = dense;
+        sparse.set((1, 2), 'z');
+        dense.set((1, 2), 'z');
+        assert_eq!(dense.render(), sparse.render_exact());
+        assert_eq!(dense.to_sparse().diff(&sparse), vec![]);
+
+        let empty: Grid<char> = Grid::empty();
+        assert!(empty.to_dense().is_none());
+    }
+
+    #[test]
+    fn test_dense_offset_origin() {
+        use super::{DenseGrid, Grid};
+
+        let mut sparse: Grid<char> = Grid::empty();
+        sparse.set((-2, 5), 'a');
+        sparse.set((0, 6), 'b');
+
+        let dense: DenseGrid<char> = sparse.to_dense().unwrap();
+        assert_eq!((dense.width(), dense.height()), (3, 2));
+        assert!(dense.contains(&(-2, 5)));
+        assert!(!dense.contains(&(1, 5)));
+        assert_eq!(dense.get(&(-2, 5)), 'a');
+        assert_eq!(dense.get(&(0, 6)), 'b');
+        assert_eq!(dense.render(), sparse.render_exact());
+    }
+
+    #[test]
+    fn test_render_with() {
+        use super::Grid;
+
+        let grid: Grid<char> = Grid::from_str_with("...\n.#.\n...", |c| c).unwrap();
+
+        let rendered = grid.render_with(|coord, value| match coord {
+            (2, 0) => '@',
+            _ => value,
+        });
+        assert_eq!(rendered, "..@\n.#.\n...\n");
+
+        let empty: Grid<char> = Grid::empty();
+        assert_eq!(empty.render_with(|_, _| '@'), "");
+    }
+
+    #[test]
+    fn test_render_negative_coords() {
+        use super::Grid;
+
+        let mut grid: Grid<char> = Grid::empty();
+        assert_eq!(grid.render_exact(), "");
+
+        grid.set((-3, -2), 'a');
+        grid.set((-1, -1), 'b');
+        grid.set((-2, -2), '.');
+        grid.set((-3, -1), '.');
+        grid.set((-2, -1), '.');
+        grid.set((-1, -2), '.');
+
+        assert_eq!(grid.render_exact(), "a..\n..b\n");
+
+        // render() still includes the origin, plus one row and column of
+        // padding.
+        let rendered: Vec<String> = grid
+            .render()
+            .lines()
+            .map(|line| line.replace('\0', " "))
+            .collect();
+        assert_eq!(rendered, vec!["a..  ", "..b  ", "     ", "     "]);
+    }
+
+    #[test]
+    fn test_direction_opposite() {
+        use super::{Direction, Turn};
+
+        assert_eq!(Direction::North.opposite(), Direction::South);
+        for &direction in Direction::all().iter() {
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!((0, 0) + direction + direction.opposite(), (0, 0));
+
+            let mut turned = direction;
+            for _ in 0..4 {
+                turned = Turn::CW.apply(turned);
+            }
+            assert_eq!(turned, direction);
+            assert_eq!(
+                Turn::CW.apply(Turn::CW.apply(direction)),
+                direction.opposite()
+            );
         }
     }
+
+    #[test]
+    fn test_iter_ordered() {
+        use super::{Coord, Grid};
+
+        let mut grid: Grid<char> = Grid::empty();
+        grid.set((1, 1), 'c');
+        grid.set((0, 0), 'a');
+        grid.set((-1, 1), 'b');
+
+        let cells: Vec<(Coord, char)> = grid.iter_ordered(false).collect();
+        assert_eq!(cells, vec![((0, 0), 'a'), ((-1, 1), 'b'), ((1, 1), 'c')]);
+
+        let coords: Vec<Coord> = grid.iter_ordered(true).map(|(c, _)| c).collect();
+        assert_eq!(
+            coords,
+            vec![(-1, 0), (0, 0), (1, 0), (-1, 1), (0, 1), (1, 1)]
+        );
+        assert_eq!(grid.iter_ordered(true).nth(2), Some(((1, 0), '\0')));
+
+        let empty: Grid<char> = Grid::empty();
+        assert_eq!(empty.iter_ordered(true).count(), 0);
+    }
+
+    #[test]
+    fn test_turn_to() {
+        use super::{Direction, Turn};
+
+        for &from in Direction::all().iter() {
+            assert_eq!(from.turn_to(from), None);
+            assert_eq!(Turn::Around.apply(from), from.opposite());
+            assert_eq!(from.turn_to(from.opposite()), Some(Turn::Around));
+
+            for &to in Direction::all().iter().filter(|&&to| to != from) {
+                let turn = from.turn_to(to).unwrap();
+                assert_eq!(turn.apply(from), to);
+            }
+        }
+
+        assert_eq!(Direction::North.turn_to(Direction::East), Some(Turn::CW));
+        assert_eq!(Direction::North.turn_to(Direction::West), Some(Turn::CCW));
+        assert_eq!(Direction::West.turn_to(Direction::North), Some(Turn::CW));
+        assert_eq!(Direction::South.turn_to(Direction::East), Some(Turn::CCW));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[allow(dead_code)]
+    enum Tile {
+        #[default]
+        Unknown,
+        Wall,
+        Open,
+    }
+
+    impl From<Tile> for char {
+        fn from(tile: Tile) -> char {
+            match tile {
+                Tile::Unknown => ' ',
+                Tile::Wall => '#',
+                Tile::Open => '.',
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    fn tile_from_char(c: char) -> Tile {
+        match c {
+            '#' => Tile::Wall,
+            '.' => Tile::Open,
+            _ => Tile::Unknown,
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        use super::Grid;
+
+        let mut grid: Grid<Tile> = Grid::empty();
+        for x in -2..=1 {
+            grid.set((x, -1), Tile::Wall);
+            grid.set((x, 1), Tile::Wall);
+        }
+        grid.set((-2, 0), Tile::Wall);
+        grid.set((-1, 0), Tile::Open);
+        // (0, 0) is left uninitialized.
+        grid.set((1, 0), Tile::Open);
+
+        let saved = grid.save();
+        assert_eq!(saved, "-2,-1\n####\n#. .\n####\n");
+
+        let loaded = Grid::load(&saved, tile_from_char).unwrap();
+        assert!(loaded.diff(&grid).is_empty());
+        assert_eq!(loaded.initialized_count(), grid.initialized_count());
+        assert_eq!(loaded.save(), saved);
+
+        let empty: Grid<Tile> = Grid::empty();
+        assert_eq!(empty.save(), "");
+        assert_eq!(
+            Grid::load("", tile_from_char).unwrap().initialized_count(),
+            0
+        );
+
+        assert!(Grid::load("1;2\n#", tile_from_char).is_err());
+        assert!(Grid::load("1,2,3\n#", tile_from_char).is_err());
+        assert!(Grid::load("0,0\n##\n#", tile_from_char).is_err());
+    }
 }
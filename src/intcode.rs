@@ -1,6 +1,10 @@
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fmt;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
 use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Copy)]
 enum Op {
@@ -16,6 +20,25 @@ enum Op {
     Exit,
 }
 
+impl Op {
+    /// Mnemonic, parameter modes, and whether the last parameter is the
+    /// address written to by the instruction.
+    fn describe(self) -> (&'static str, Vec<ParameterMode>, bool) {
+        match self {
+            Op::Add(a, b, c) => ("ADD", vec![a, b, c], true),
+            Op::Mul(a, b, c) => ("MUL", vec![a, b, c], true),
+            Op::Input(a) => ("IN", vec![a], true),
+            Op::Output(a) => ("OUT", vec![a], false),
+            Op::JumpIfTrue(a, b) => ("JT", vec![a, b], false),
+            Op::JumpIfFalse(a, b) => ("JF", vec![a, b], false),
+            Op::LessThan(a, b, c) => ("LT", vec![a, b, c], true),
+            Op::EqualTo(a, b, c) => ("EQ", vec![a, b, c], true),
+            Op::AdjustRelativeBase(a) => ("ARB", vec![a], false),
+            Op::Exit => ("HALT", vec![], false),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum ParameterMode {
     Position,
@@ -32,6 +55,14 @@ impl ParameterMode {
             _ => None,
         }
     }
+
+    fn format_param(self, param: i64) -> String {
+        match self {
+            Self::Position => format!("[{}]", param),
+            Self::Immediate => format!("#{}", param),
+            Self::Relative => format!("@{}", param),
+        }
+    }
 }
 
 fn hundreds_digit(i: i64) -> i64 {
@@ -65,6 +96,689 @@ mod tests {
         assert_eq!(super::thousands_digit(1504), 1);
         assert_eq!(super::thousands_digit(2504), 2);
     }
+
+    #[test]
+    fn test_run_concurrently_outcomes() {
+        use super::{ConcurrentOutcome, Program, StaticIO};
+
+        // Echo one input, then halt.
+        let program: Program = "3,0,4,0,99".parse().unwrap();
+
+        let mut ios = vec![StaticIO::new(vec![5]), StaticIO::new(vec![])];
+        let outcomes = program.run_concurrently(&mut ios).unwrap();
+
+        assert_eq!(
+            outcomes,
+            vec![ConcurrentOutcome::Halted, ConcurrentOutcome::StalledOnInput]
+        );
+
+        let mut ios = ios.into_iter();
+        assert_eq!(ios.next().unwrap().outputs(), vec![5]);
+        assert_eq!(ios.next().unwrap().outputs(), vec![]);
+    }
+
+    #[test]
+    fn test_run_concurrently_collecting() {
+        use super::{ConcurrentOutcome, Program, IO};
+        use std::cell::RefCell;
+        use std::collections::VecDeque;
+        use std::rc::Rc;
+
+        type Queue = Rc<RefCell<VecDeque<i64>>>;
+
+        #[derive(Debug)]
+        struct PipeIO {
+            input: Queue,
+            output: Queue,
+        }
+
+        impl IO for PipeIO {
+            fn input(&mut self) -> Option<i64> {
+                self.input.borrow_mut().pop_front()
+            }
+
+            fn output(&mut self, value: i64) -> Option<()> {
+                self.output.borrow_mut().push_back(value);
+                Some(())
+            }
+        }
+
+        // Three times: read a value and output it plus one.
+        let program: Program =
+            "3,100,1001,100,1,100,4,100,1001,101,1,101,1007,101,3,102,1005,102,0,99"
+                .parse()
+                .unwrap();
+
+        // Two amps in a feedback loop, with an initial signal of 0.
+        let a_in: Queue = Rc::new(RefCell::new(vec![0].into()));
+        let b_in: Queue = Rc::new(RefCell::new(VecDeque::new()));
+        let mut ios = vec![
+            PipeIO {
+                input: a_in.clone(),
+                output: b_in.clone(),
+            },
+            PipeIO {
+                input: b_in,
+                output: a_in,
+            },
+        ];
+
+        let (outcomes, outputs) = program.run_concurrently_collecting(&mut ios).unwrap();
+        assert_eq!(outcomes, vec![ConcurrentOutcome::Halted; 2]);
+        assert_eq!(outputs, vec![vec![1, 3, 5], vec![2, 4, 6]]);
+        assert_eq!(outputs.last().unwrap().last(), Some(&6));
+    }
+
+    #[test]
+    fn test_run_with_overrides() {
+        use super::Program;
+
+        let program: Program = "1,9,10,3,2,3,11,0,99,30,40,50".parse().unwrap();
+        assert_eq!(program.run_with_overrides(&[], 0).unwrap(), 3500);
+        assert_eq!(program.run_with_overrides(&[], 3).unwrap(), 70);
+
+        // Swap the second input of the add to point at 50 instead of 40.
+        assert_eq!(program.run_with_overrides(&[(2, 11)], 3).unwrap(), 80);
+
+        // The original program is unchanged.
+        assert_eq!(program.run_with_overrides(&[], 0).unwrap(), 3500);
+
+        let program: Program = "2,4,4,5,99,0".parse().unwrap();
+        assert_eq!(program.run_with_overrides(&[], 5).unwrap(), 9801);
+        assert_eq!(program.run_problem2(4, 4, 5).unwrap(), 9801);
+
+        assert!(program.run_with_overrides(&[(6, 1)], 0).is_err());
+        assert!(program.run_with_overrides(&[], 6).is_err());
+    }
+
+    #[test]
+    fn test_run_with_overrides_in_reused_buffer() {
+        use super::Program;
+
+        let program: Program = "1,9,10,3,2,3,11,0,99,30,40,50".parse().unwrap();
+        let mut memory = program.with_memory();
+
+        // Some of these pairs produce invalid programs, which should fail the
+        // same way in both cases and not poison later runs.
+        for noun in 0..12 {
+            for verb in 0..12 {
+                let overrides = [(1, noun), (2, verb)];
+                let fresh = program.run_with_overrides(&overrides, 0);
+                let reused = program.run_with_overrides_in(&mut memory, &overrides, 0);
+                assert_eq!(fresh.ok(), reused.ok());
+            }
+        }
+
+        assert_eq!(
+            program.run_with_overrides_in(&mut memory, &[], 0).unwrap(),
+            3500
+        );
+        assert_eq!(memory[3], 70);
+    }
+
+    #[test]
+    fn test_parse_with_whitespace() {
+        use super::Program;
+
+        let program: Program = "1, 2,\n3 , 99".parse().unwrap();
+        assert_eq!(program.code, vec![1, 2, 3, 99]);
+
+        let program: Program = "1,2,3,99,\n\n".parse().unwrap();
+        assert_eq!(program.code, vec![1, 2, 3, 99]);
+
+        // Empty fields in the middle would shift every later address.
+        assert!("1,,2".parse::<Program>().is_err());
+        assert!("1 -2\n3\t99".parse::<Program>().is_err());
+        assert!("1,2,three,99".parse::<Program>().is_err());
+        assert!("1,2,3-,99".parse::<Program>().is_err());
+    }
+
+    #[test]
+    fn test_absurd_address_is_out_of_bounds() {
+        use super::{ExecuteError, Machine, Program, StaticIO};
+
+        // Move the relative base far away, then write relative to it.
+        let program: Program = "109,1000000000,21101,1,1,0,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(1000000000)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // Reads are limited too.
+        let program: Program = "4,1000000000,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(1000000000)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // The limit is configurable.
+        let program: Program = "1101,1,1,100,99".parse().unwrap();
+        let mut machine = Machine::new(&program);
+        machine.set_max_memory(50);
+        match machine.run_until_output() {
+            Err(ExecuteError::OutOfBounds(100)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_truncated_program_is_out_of_bounds() {
+        use super::{ExecuteError, Program, StaticIO};
+
+        // Add missing its last two parameters.
+        let program: Program = "1,0".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(2)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // Output missing its parameter.
+        let program: Program = "4".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(1)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // Input missing its parameter.
+        let program: Program = "3".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![7])) {
+            Err(ExecuteError::OutOfBounds(1)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // Jump past the end of the program.
+        let program: Program = "1105,1,99999".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(99999)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+
+        // Run off the end without halting.
+        let program: Program = "1101,1,1,0".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::OutOfBounds(4)) => {}
+            other => panic!("Expected OutOfBounds, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_execute_error_display() {
+        use super::{ExecuteError, Program, StaticIO};
+
+        let program: Program = "1,0,0,0,37".parse().unwrap();
+        let err = program.run(&mut StaticIO::new(vec![])).unwrap_err();
+        assert_eq!(err.to_string(), "bad opcode 37 at position 4");
+
+        assert_eq!(
+            ExecuteError::OutOfBounds(99999).to_string(),
+            "access out of bounds at address 99999"
+        );
+    }
+
+    #[test]
+    fn test_address_errors() {
+        use super::{ExecuteError, Program, StaticIO};
+
+        // Add with an immediate mode destination.
+        let program: Program = "10001,0,0,0,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::ImmediateWriteTarget { pos: 0 }) => {}
+            other => panic!("Expected ImmediateWriteTarget, got {:?}", other),
+        }
+
+        // Input with an immediate mode destination.
+        let program: Program = "104,1,103,0,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![5])) {
+            Err(ExecuteError::ImmediateWriteTarget { pos: 2 }) => {}
+            other => panic!("Expected ImmediateWriteTarget, got {:?}", other),
+        }
+
+        // Output from a negative position.
+        let program: Program = "4,-3,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::NegativeAddress { value: -3, pos: 0 }) => {}
+            other => panic!("Expected NegativeAddress, got {:?}", other),
+        }
+
+        // Move the relative base below zero, then write relative to it.
+        let program: Program = "109,-10,21101,1,1,4,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::NegativeAddress { value: -6, pos: 2 }) => {}
+            other => panic!("Expected NegativeAddress, got {:?}", other),
+        }
+
+        // Move the relative base below zero, then output relative to it.
+        let program: Program = "109,-10,204,5,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::NegativeAddress { value: -5, pos: 2 }) => {}
+            other => panic!("Expected NegativeAddress, got {:?}", other),
+        }
+
+        // Offsets past the smallest i64 don't wrap around.
+        let program: Program = "109,-9223372036854775808,204,-1,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::NegativeAddress {
+                value: i64::MIN,
+                pos: 2,
+            }) => {}
+            other => panic!("Expected NegativeAddress, got {:?}", other),
+        }
+
+        // Jump to a negative position.
+        let program: Program = "1105,1,-1".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::NegativeAddress { value: -1, pos: 0 }) => {}
+            other => panic!("Expected NegativeAddress, got {:?}", other),
+        }
+
+        // Two adjustments whose sum doesn't fit in an i64.
+        let program: Program = "109,9223372036854775807,109,1,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::RelativeBaseOverflow { pos: 2 }) => {}
+            other => panic!("Expected RelativeBaseOverflow, got {:?}", other),
+        }
+        let program: Program = "109,-9223372036854775808,109,-1,99".parse().unwrap();
+        match program.run(&mut StaticIO::new(vec![])) {
+            Err(ExecuteError::RelativeBaseOverflow { pos: 2 }) => {}
+            other => panic!("Expected RelativeBaseOverflow, got {:?}", other),
+        }
+
+        assert_eq!(
+            ExecuteError::RelativeBaseOverflow { pos: 2 }.to_string(),
+            "relative base overflowed at position 2"
+        );
+        assert_eq!(
+            ExecuteError::NegativeAddress { value: -6, pos: 2 }.to_string(),
+            "negative address -6 at position 2"
+        );
+        assert_eq!(
+            ExecuteError::ImmediateWriteTarget { pos: 7 }.to_string(),
+            "immediate mode write target at position 7"
+        );
+    }
+
+    #[test]
+    fn test_run_io_counted() {
+        use super::Program;
+
+        let program: Program = "3,0,4,0,99".parse().unwrap();
+        assert_eq!(program.run_io_counted(vec![7]).unwrap(), (vec![7], 3));
+        assert_eq!(program.run_io(vec![8]).unwrap(), vec![8]);
+
+        // Compare input with 8, using a jump.
+        let program: Program = "3,3,1105,-1,9,1101,0,0,12,4,12,99,1".parse().unwrap();
+        assert_eq!(program.run_io_counted(vec![0]).unwrap(), (vec![0], 5));
+        assert_eq!(program.run_io_counted(vec![5]).unwrap(), (vec![1], 4));
+    }
+
+    #[test]
+    fn test_from_slice() {
+        use super::Program;
+
+        let program = Program::from_slice(&[99]);
+        assert_eq!(program.run_io(vec![]).unwrap(), vec![]);
+
+        let program = Program::from_slice(&[104, 5, 99]);
+        assert_eq!(program.run_io(vec![]).unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn test_machine() {
+        use super::{Machine, Program};
+
+        // Repeatedly read a value and output twice its value.
+        let program: Program = "3,100,1002,100,2,100,4,100,1105,1,0".parse().unwrap();
+        let mut machine = Machine::new(&program);
+
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert!(machine.is_awaiting_input());
+        assert!(!machine.is_halted());
+
+        machine.provide_input(3);
+        machine.provide_input(5);
+        assert_eq!(machine.run_until_output().unwrap(), Some(6));
+        assert_eq!(machine.run_until_output().unwrap(), Some(10));
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert!(machine.is_awaiting_input());
+
+        machine.provide_input(-1);
+        assert_eq!(machine.run_until_output().unwrap(), Some(-2));
+
+        // Output two values, then halt.
+        let program: Program = "104,7,104,8,99".parse().unwrap();
+        let mut machine = Machine::new(&program);
+        machine.step().unwrap();
+        assert_eq!(machine.run_until_output().unwrap(), Some(7));
+        assert_eq!(machine.run_until_output().unwrap(), Some(8));
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert!(machine.is_halted());
+        assert!(!machine.is_awaiting_input());
+    }
+
+    #[test]
+    fn test_channel_io_ping_pong() {
+        use super::{ChannelIO, Program};
+        use std::thread;
+
+        // Three times: read a value and output it plus one.
+        let program: Program =
+            "3,100,1001,100,1,100,4,100,1001,101,1,101,1007,101,3,102,1005,102,0,99"
+                .parse()
+                .unwrap();
+
+        let ios = ChannelIO::ring(vec![vec![0], vec![]]);
+        let finished: Vec<ChannelIO> = thread::scope(|scope| {
+            let handles: Vec<_> = ios
+                .into_iter()
+                .map(|mut io| {
+                    let program = &program;
+                    scope.spawn(move || {
+                        program.run(&mut io).unwrap();
+                        io
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        // The first program sees 0, 2, 4, and the second sees 1, 3, 5. The
+        // second program's final output is left unread by the first.
+        assert_eq!(finished[0].unread_inputs(), vec![6]);
+        assert_eq!(finished[1].unread_inputs(), vec![]);
+    }
+
+    #[test]
+    fn test_channel_io_disconnected() {
+        use super::{ChannelIO, ExecuteError, Program};
+        use std::sync::mpsc::channel;
+
+        let (in_sender, in_receiver) = channel();
+        let (out_sender, out_receiver) = channel();
+        in_sender.send(42).unwrap();
+        drop(in_sender);
+
+        // Echo a value, then try to read another. The input channel is closed,
+        // so the second read fails instead of blocking forever.
+        let program: Program = "3,100,4,100,3,100,99".parse().unwrap();
+        let mut io = ChannelIO::new(in_receiver, out_sender);
+        assert!(matches!(
+            program.run(&mut io),
+            Err(ExecuteError::InputError)
+        ));
+        assert_eq!(out_receiver.try_iter().collect::<Vec<i64>>(), vec![42]);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        use super::Program;
+
+        let program: Program = "1,10,20,30,1101,3,4,5,3,9,204,-1,109,19,1005,7,0,99,-5,7,1"
+            .parse()
+            .unwrap();
+
+        let expected = "\
+0000: ADD [10] [20] -> [30]
+0004: ADD #3 #4 -> [5]
+0008: IN -> [9]
+0010: OUT @-1
+0012: ARB #19
+0014: JT [7] #0
+0017: HALT
+0018: DATA -5
+0019: DATA 7
+0020: DATA 1
+";
+        assert_eq!(program.disassemble(), expected);
+    }
+
+    #[test]
+    fn test_trace() {
+        use super::Program;
+        use std::sync::{Arc, Mutex};
+
+        // Read x, set the relative base to 3, then output (x + 10) * 4.
+        let program: Program = "3,15,109,3,1001,15,10,16,22202,13,14,14,4,17,99,0,0,4"
+            .parse()
+            .unwrap();
+
+        // The multiply overwrites its second parameter, but the trace shows
+        // the value from before it ran.
+        let (outputs, trace) = program.trace_io(vec![5]).unwrap();
+        assert_eq!(outputs, vec![60]);
+        assert_eq!(
+            trace,
+            vec![
+                "0000: IN -> [15]  rb=0",
+                "0002: ARB #3  rb=0",
+                "0004: ADD [15]=5 #10 -> [16]  rb=3",
+                "0008: MUL @13[16]=15 @14[17]=4 -> @14[17]  rb=3",
+                "0012: OUT [17]=60  rb=3",
+                "0014: HALT  rb=3",
+            ]
+        );
+
+        let mut machine = super::Machine::new(&"3,5,4,5,99".parse().unwrap());
+        let lines = Arc::new(Mutex::new(vec![]));
+        {
+            let lines = lines.clone();
+            machine.set_trace(move |line| lines.lock().unwrap().push(line));
+        }
+
+        // Waiting for input doesn't add to the trace.
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert!(lines.lock().unwrap().is_empty());
+
+        machine.provide_input(7);
+        assert_eq!(machine.run_until_output().unwrap(), Some(7));
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec!["0000: IN -> [5]  rb=0", "0002: OUT [5]=7  rb=0"]
+        );
+    }
+
+    #[test]
+    fn test_machine_is_send() {
+        fn assert_send<T: Send>() {}
+
+        // Tracing mustn't stop machines from being moved between threads.
+        assert_send::<super::Machine>();
+    }
+
+    #[test]
+    fn test_step_limit() {
+        use super::{ExecuteError, Machine, NoIO, Program, StaticIO};
+
+        // Jump back to position 0 forever.
+        let program: Program = "1105,1,0".parse().unwrap();
+        match program.run_with_limit(&mut NoIO, 1000) {
+            Err(ExecuteError::StepLimitExceeded(1000)) => {}
+            other => panic!("Expected step limit error, got {:?}", other),
+        }
+        assert_eq!(
+            ExecuteError::StepLimitExceeded(1000).to_string(),
+            "program didn't halt within 1000 steps"
+        );
+
+        // Add, output, exit.
+        let program: Program = "1101,2,3,7,4,7,99,0".parse().unwrap();
+        assert_eq!(
+            program
+                .run_with_limit(&mut StaticIO::new(vec![]), 3)
+                .unwrap(),
+            3
+        );
+        assert!(program
+            .run_with_limit(&mut StaticIO::new(vec![]), 2)
+            .is_err());
+
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run_until_output().unwrap(), Some(5));
+        assert_eq!(machine.steps(), 2);
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert_eq!(machine.steps(), 3);
+    }
+
+    #[test]
+    fn test_stdin_io() {
+        use super::{Program, StdinIO};
+        use std::io::Cursor;
+
+        // Add two inputs and output the result.
+        let program: Program = "3,100,3,101,1,100,101,102,4,102,99".parse().unwrap();
+
+        let mut io = StdinIO::with_streams(Cursor::new("3\nfoo\n\n 4 \n"), vec![]);
+        program.run(&mut io).unwrap();
+        let written = String::from_utf8(io.into_writer()).unwrap();
+        assert_eq!(written, "Invalid input: \"foo\"\nInvalid input: \"\"\n7\n");
+
+        // Running out of input is an error for the program.
+        let mut io = StdinIO::with_streams(Cursor::new("3\n"), vec![]);
+        assert!(program.run(&mut io).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        use super::{Machine, Program};
+
+        // Move the relative base to 50, then repeatedly read a value, add it
+        // to a running total, and output the total.
+        let program: Program = "109,50,203,0,22201,0,1,1,204,1,1105,1,2".parse().unwrap();
+        let mut machine = Machine::new(&program);
+
+        let start = machine.snapshot();
+        assert_eq!(start.relative_base, 0);
+
+        machine.provide_input(1);
+        machine.provide_input(2);
+        assert_eq!(machine.run_until_output().unwrap(), Some(1));
+        assert_eq!(machine.run_until_output().unwrap(), Some(3));
+
+        let middle = machine.snapshot();
+        assert_eq!(middle.relative_base, 50);
+        assert_eq!(middle.pos, 10);
+
+        machine.provide_input(10);
+        assert_eq!(machine.run_until_output().unwrap(), Some(13));
+        assert_ne!(machine.snapshot(), middle);
+
+        // Replaying the same input from the snapshot gives the same results.
+        machine.restore(&middle);
+        assert_eq!(machine.snapshot(), middle);
+        machine.provide_input(10);
+        machine.provide_input(1);
+        assert_eq!(machine.run_until_output().unwrap(), Some(13));
+        assert_eq!(machine.run_until_output().unwrap(), Some(14));
+
+        machine.restore(&start);
+        assert_eq!(machine.snapshot(), start);
+        machine.provide_input(4);
+        assert_eq!(machine.run_until_output().unwrap(), Some(4));
+    }
+
+    #[test]
+    fn test_static_io_starved() {
+        use super::{ExecuteError, Program, StaticIO};
+
+        // Add two inputs and output the result.
+        let program: Program = "3,100,3,101,1,100,101,102,4,102,99".parse().unwrap();
+
+        let mut io = StaticIO::new(vec![1]);
+        match program.run(&mut io) {
+            Err(ExecuteError::InputError) => {}
+            other => panic!("Expected InputError, got {:?}", other),
+        }
+        assert!(io.was_starved());
+
+        let mut io = StaticIO::new(vec![1, 2]);
+        program.run(&mut io).unwrap();
+        assert!(!io.was_starved());
+        assert_eq!(io.outputs(), vec![3]);
+    }
+
+    #[test]
+    fn test_fork() {
+        use super::{Machine, Program};
+
+        // Read two values and output their sum, then repeat.
+        let program: Program = "3,100,3,101,1,100,101,102,4,102,1105,1,0".parse().unwrap();
+        let mut machine = Machine::new(&program);
+        machine.provide_input(10);
+        assert_eq!(machine.run_until_output().unwrap(), None);
+        assert!(machine.is_awaiting_input());
+        let paused = machine.snapshot();
+
+        let mut left = machine.fork();
+        let mut right = machine.fork();
+        left.provide_input(1);
+        right.provide_input(5);
+        assert_eq!(left.run_until_output().unwrap(), Some(11));
+        assert_eq!(right.run_until_output().unwrap(), Some(15));
+
+        // The forks keep running independently.
+        left.provide_input(2);
+        left.provide_input(3);
+        assert_eq!(left.run_until_output().unwrap(), Some(5));
+        assert_eq!(right.run_until_output().unwrap(), None);
+
+        // The original is still waiting for its second value.
+        assert!(machine.is_awaiting_input());
+        assert_eq!(machine.snapshot(), paused);
+
+        // Queued inputs are copied to the fork.
+        machine.provide_input(100);
+        let mut queued = machine.fork();
+        assert_eq!(queued.run_until_output().unwrap(), Some(110));
+        assert_eq!(machine.run_until_output().unwrap(), Some(110));
+    }
+
+    #[test]
+    fn test_decode_ascii() {
+        let outputs = [72, 105, 10, 19690720, 33, -1];
+        let (text, others) = super::decode_ascii(&outputs);
+        assert_eq!(text, "Hi\n!");
+        assert_eq!(others, vec![19690720, -1]);
+    }
+
+    #[test]
+    fn test_transcript_io() {
+        use super::{Program, TranscriptIO};
+
+        // Print "> ", then echo input characters until a newline is echoed.
+        let program: Program = "104,62,104,32,3,100,4,100,1008,100,10,101,1006,101,4,99"
+            .parse()
+            .unwrap();
+
+        let mut io = TranscriptIO::new(vec!["hello", "unused"]);
+        program.run(&mut io).unwrap();
+        assert_eq!(io.transcript(), "> hello\nhello\n");
+    }
+
+    #[test]
+    fn test_ascii_io() {
+        use super::{AsciiIO, Program};
+
+        // Print "> ", then echo input characters until a newline is echoed.
+        let program: Program = "104,62,104,32,3,100,4,100,1008,100,10,101,1006,101,4,99"
+            .parse()
+            .unwrap();
+
+        let mut io = AsciiIO::new();
+        io.push_line("hi");
+        program.run(&mut io).unwrap();
+        assert_eq!(io.take_output(), "> hi\n");
+        assert_eq!(io.take_output(), "");
+
+        // The next exchange starts fresh.
+        io.push_line("bye");
+        program.run(&mut io).unwrap();
+        assert_eq!(io.take_output(), "> bye\n");
+
+        // Non-ASCII values get their own line.
+        let program: Program = "104,79,104,75,104,19690720,99".parse().unwrap();
+        program.run(&mut io).unwrap();
+        assert_eq!(io.take_output(), "OK19690720\n");
+    }
 }
 
 fn first_parameter_mode(i: i64) -> Option<ParameterMode> {
@@ -119,11 +833,30 @@ pub enum ExecuteError {
     BadOp { code: i64, pos: usize },
     InputError,
     OutputError,
+    StepLimitExceeded(usize),
+    ImmediateWriteTarget { pos: usize },
+    NegativeAddress { value: i64, pos: usize },
+    RelativeBaseOverflow { pos: usize },
 }
 
 impl fmt::Display for ExecuteError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            OutOfBounds(addr) => write!(f, "access out of bounds at address {}", addr),
+            BadOp { code, pos } => write!(f, "bad opcode {} at position {}", code, pos),
+            InputError => write!(f, "program requested input, but none was available"),
+            OutputError => write!(f, "program output was rejected"),
+            StepLimitExceeded(limit) => write!(f, "program didn't halt within {} steps", limit),
+            ImmediateWriteTarget { pos } => {
+                write!(f, "immediate mode write target at position {}", pos)
+            }
+            NegativeAddress { value, pos } => {
+                write!(f, "negative address {} at position {}", value, pos)
+            }
+            RelativeBaseOverflow { pos } => {
+                write!(f, "relative base overflowed at position {}", pos)
+            }
+        }
     }
 }
 
@@ -135,6 +868,10 @@ use self::ExecuteError::*;
 /// Result type for program executions.
 pub type ExecuteResult<T> = Result<T, ExecuteError>;
 
+/// Default limit on the number of memory cells a program may use, so that a
+/// bad address fails with `OutOfBounds` instead of exhausting memory.
+pub const DEFAULT_MAX_MEMORY: usize = 1 << 24;
+
 #[derive(Debug)]
 pub struct ParseError(String);
 
@@ -151,6 +888,16 @@ pub trait IO: fmt::Debug {
     fn output(&mut self, value: i64) -> Option<()>;
 }
 
+impl<T: IO + ?Sized> IO for &mut T {
+    fn input(&mut self) -> Option<i64> {
+        (**self).input()
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        (**self).output(value)
+    }
+}
+
 #[derive(Debug)]
 struct NoIO;
 
@@ -168,6 +915,8 @@ impl IO for NoIO {
 pub struct StaticIO {
     inputs: Vec<i64>,
     outputs: Vec<i64>,
+    /// Did the program ask for input after all inputs were used up?
+    starved: bool,
 }
 
 impl StaticIO {
@@ -177,17 +926,31 @@ impl StaticIO {
         StaticIO {
             inputs,
             outputs: vec![],
+            starved: false,
         }
     }
 
     pub fn outputs(self) -> Vec<i64> {
         self.outputs
     }
+
+    /// Did the program ask for more inputs than it was given?
+    ///
+    /// If a run failed with `InputError`, this distinguishes a too-short
+    /// input list from other IO failures.
+    #[allow(dead_code)]
+    pub fn was_starved(&self) -> bool {
+        self.starved
+    }
 }
 
 impl IO for StaticIO {
     fn input(&mut self) -> Option<i64> {
-        self.inputs.pop()
+        let value = self.inputs.pop();
+        if value.is_none() {
+            self.starved = true;
+        }
+        value
     }
 
     fn output(&mut self, value: i64) -> Option<()> {
@@ -196,6 +959,269 @@ impl IO for StaticIO {
     }
 }
 
+/// IO that reads inputs from a line-based reader (stdin by default) and
+/// writes outputs to a writer (stdout by default), for playing programs by
+/// hand.
+///
+/// Each input is read from its own line. Lines that don't parse as integers
+/// are reported and skipped. `input()` returns None at end of file.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct StdinIO<R: BufRead + fmt::Debug = StdinLock<'static>, W: Write + fmt::Debug = Stdout> {
+    reader: R,
+    writer: W,
+}
+
+#[allow(dead_code)]
+impl StdinIO {
+    pub fn new() -> StdinIO {
+        StdinIO::with_streams(io::stdin().lock(), io::stdout())
+    }
+}
+
+#[allow(dead_code)]
+impl<R: BufRead + fmt::Debug, W: Write + fmt::Debug> StdinIO<R, W> {
+    pub fn with_streams(reader: R, writer: W) -> StdinIO<R, W> {
+        StdinIO { reader, writer }
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<R: BufRead + fmt::Debug, W: Write + fmt::Debug> IO for StdinIO<R, W> {
+    fn input(&mut self) -> Option<i64> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+
+            match line.trim().parse() {
+                Ok(value) => return Some(value),
+                Err(_) => {
+                    writeln!(self.writer, "Invalid input: {:?}", line.trim()).ok()?;
+                    self.writer.flush().ok()?;
+                }
+            }
+        }
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        writeln!(self.writer, "{}", value).ok()?;
+        self.writer.flush().ok()
+    }
+}
+
+/// IO that forwards to another IO, keeping a copy of every accepted output.
+#[derive(Debug)]
+struct RecordingIO<T: IO> {
+    inner: T,
+    outputs: Vec<i64>,
+}
+
+impl<T: IO> RecordingIO<T> {
+    fn new(inner: T) -> RecordingIO<T> {
+        RecordingIO {
+            inner,
+            outputs: vec![],
+        }
+    }
+}
+
+impl<T: IO> IO for RecordingIO<T> {
+    fn input(&mut self) -> Option<i64> {
+        self.inner.input()
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        self.inner.output(value)?;
+        self.outputs.push(value);
+        Some(())
+    }
+}
+
+/// IO backed by channels, for running programs concurrently on separate
+/// threads.
+///
+/// `input()` blocks until a value is available. It returns None only once
+/// every sender for this IO's input channel has been dropped and all sent
+/// values have been read.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct ChannelIO {
+    receiver: Receiver<i64>,
+    sender: Sender<i64>,
+}
+
+#[allow(dead_code)]
+impl ChannelIO {
+    pub fn new(receiver: Receiver<i64>, sender: Sender<i64>) -> ChannelIO {
+        ChannelIO { receiver, sender }
+    }
+
+    /// Construct IOs for programs connected in a ring, where the outputs of
+    /// each program are the inputs to the next, and the outputs of the last
+    /// program are the inputs to the first.
+    ///
+    /// `initial_inputs[i]` are queued as the first inputs to program `i`.
+    pub fn ring(initial_inputs: Vec<Vec<i64>>) -> Vec<ChannelIO> {
+        let n = initial_inputs.len();
+        let (senders, receivers): (Vec<Sender<i64>>, Vec<Receiver<i64>>) =
+            (0..n).map(|_| channel()).unzip();
+
+        for (sender, inputs) in senders.iter().zip(initial_inputs) {
+            for value in inputs {
+                // Can't fail: we're still holding the receiver.
+                sender.send(value).unwrap();
+            }
+        }
+
+        // Program i reads from channel i and writes to channel i + 1.
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(i, receiver)| ChannelIO::new(receiver, senders[(i + 1) % n].clone()))
+            .collect()
+    }
+
+    /// Drain any values that were sent to this IO but never read.
+    pub fn unread_inputs(&self) -> Vec<i64> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl IO for ChannelIO {
+    fn input(&mut self) -> Option<i64> {
+        self.receiver.recv().ok()
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        self.sender.send(value).ok()
+    }
+}
+
+/// IO for ASCII programs that feeds scripted lines of input and records the
+/// full session, with input and output interleaved in the order they occur.
+///
+/// Each input line is added to the transcript when the program starts
+/// reading it. Non-ASCII outputs are recorded as decimal numbers on their
+/// own line.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct TranscriptIO {
+    lines: Vec<String>,
+    pending: Vec<u8>,
+    transcript: String,
+}
+
+#[allow(dead_code)]
+impl TranscriptIO {
+    pub fn new<S: Into<String>>(lines: Vec<S>) -> TranscriptIO {
+        let mut lines: Vec<String> = lines.into_iter().map(Into::into).collect();
+        // Reverse so that lines are popped in the order they were passed.
+        lines.reverse();
+        TranscriptIO {
+            lines,
+            pending: vec![],
+            transcript: String::new(),
+        }
+    }
+
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+}
+
+impl IO for TranscriptIO {
+    fn input(&mut self) -> Option<i64> {
+        if self.pending.is_empty() {
+            let mut line = self.lines.pop()?;
+            line.push('\n');
+            self.transcript.push_str(&line);
+            self.pending = line.into_bytes();
+            self.pending.reverse();
+        }
+        self.pending.pop().map(i64::from)
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        if (0..=127).contains(&value) {
+            self.transcript.push(value as u8 as char);
+        } else {
+            self.transcript.push_str(&format!("{}\n", value));
+        }
+        Some(())
+    }
+}
+
+/// IO for ASCII programs, with line-oriented input and output.
+///
+/// Lines queued with `push_line` are fed to the program one byte at a time.
+/// Output is collected as text until it's taken with `take_output`.
+/// Non-ASCII outputs are recorded as decimal numbers on their own line.
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+pub struct AsciiIO {
+    input: VecDeque<i64>,
+    output: String,
+}
+
+#[allow(dead_code)]
+impl AsciiIO {
+    pub fn new() -> AsciiIO {
+        AsciiIO::default()
+    }
+
+    /// Queue a line of input. The newline ending it is added automatically.
+    pub fn push_line(&mut self, line: &str) {
+        self.input.extend(line.bytes().map(i64::from));
+        self.input.push_back(i64::from(b'\n'));
+    }
+
+    /// Take all output written since the last call.
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl IO for AsciiIO {
+    fn input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        if (0..=127).contains(&value) {
+            self.output.push(value as u8 as char);
+        } else {
+            self.output.push_str(&format!("{}\n", value));
+        }
+        Some(())
+    }
+}
+
+/// Split program outputs into ASCII text and non-ASCII values.
+///
+/// Values in the range 0..=127 are decoded as characters. Any other values
+/// (e.g. large numeric answers) are returned separately, in order.
+#[allow(dead_code)]
+pub fn decode_ascii(outputs: &[i64]) -> (String, Vec<i64>) {
+    let mut text = String::new();
+    let mut others = vec![];
+
+    for &value in outputs {
+        if (0..=127).contains(&value) {
+            text.push(value as u8 as char);
+        } else {
+            others.push(value);
+        }
+    }
+
+    (text, others)
+}
+
 /// An IntCode program.
 pub struct Program {
     code: Vec<i64>,
@@ -207,38 +1233,183 @@ impl Program {
         Program { code: code }
     }
 
+    /// Construct a Program by copying a slice of i64s.
+    #[allow(dead_code)]
+    pub fn from_slice(code: &[i64]) -> Program {
+        Program::new(code.to_vec())
+    }
+
     /// Run a single instance of the program to completion.
     pub fn run<T: IO>(&self, io: &mut T) -> ExecuteResult<()> {
         Execution::new(self.code.clone(), io).run_to_completion()?;
         Ok(())
     }
 
-    /// Run multiple instances of the program until all programs have halted.
-    /// Whenever a program performs output, switch between programs.
-    pub fn run_concurrently<T: IO>(&self, ios: &mut Vec<T>) -> ExecuteResult<()> {
-        use std::collections::VecDeque;
+    /// Run a single instance of the program with the given inputs, returning
+    /// its outputs.
+    pub fn run_io(&self, inputs: Vec<i64>) -> ExecuteResult<Vec<i64>> {
+        self.run_io_counted(inputs).map(|(outputs, _)| outputs)
+    }
+
+    /// Like `run_io`, but also return the number of instructions executed
+    /// (including the final exit instruction).
+    pub fn run_io_counted(&self, inputs: Vec<i64>) -> ExecuteResult<(Vec<i64>, u64)> {
+        let mut io = StaticIO::new(inputs);
+        let steps = {
+            let mut execution = Execution::new(self.code.clone(), &mut io);
+            execution.run_until_halted()?;
+            execution.steps
+        };
+
+        Ok((io.outputs(), steps))
+    }
+
+    /// Run a single instance of the program to completion, failing with
+    /// `StepLimitExceeded` if it hasn't halted after `max_steps` instructions.
+    ///
+    /// Returns the number of instructions executed (including the final exit
+    /// instruction).
+    #[allow(dead_code)]
+    pub fn run_with_limit<T: IO>(&self, io: &mut T, max_steps: usize) -> ExecuteResult<u64> {
+        let mut execution = Execution::new(self.code.clone(), io);
+        execution.run_until_halted_with_limit(Some(max_steps))?;
+        Ok(execution.steps)
+    }
+
+    /// Like `run`, but call `trace` with a line describing each instruction
+    /// before it's executed. See `Execution::set_trace` for the format.
+    #[allow(dead_code)]
+    pub fn run_traced<T, F>(&self, io: &mut T, trace: F) -> ExecuteResult<()>
+    where
+        T: IO,
+        F: FnMut(String) + Send + 'static,
+    {
+        let mut execution = Execution::new(self.code.clone(), io);
+        execution.set_trace(trace);
+        execution.run_until_halted()
+    }
+
+    /// Like `run_io`, but also return the trace of executed instructions.
+    #[allow(dead_code)]
+    pub fn trace_io(&self, inputs: Vec<i64>) -> ExecuteResult<(Vec<i64>, Vec<String>)> {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let mut io = StaticIO::new(inputs);
+        {
+            let lines = lines.clone();
+            self.run_traced(&mut io, move |line| lines.lock().unwrap().push(line))?;
+        }
+
+        let lines = lines.lock().unwrap().clone();
+        Ok((io.outputs(), lines))
+    }
+
+    /// Run multiple instances of the program until all programs have halted
+    /// or are stuck waiting for input. Whenever a program performs output or
+    /// blocks on input, switch between programs.
+    ///
+    /// Returns the outcome for each program, in the same order as `ios`.
+    pub fn run_concurrently<T: IO>(
+        &self,
+        ios: &mut Vec<T>,
+    ) -> ExecuteResult<Vec<ConcurrentOutcome>> {
+        let mut outcomes = vec![ConcurrentOutcome::StalledOnInput; ios.len()];
 
-        let mut run_queue: VecDeque<Execution<T>> = ios
+        let mut run_queue: VecDeque<(usize, Execution<&mut T>)> = ios
             .iter_mut()
             .map(|io| Execution::new(self.code.clone(), io))
+            .enumerate()
             .collect();
 
-        while let Some(mut execution) = run_queue.pop_front() {
+        // Number of consecutive programs that were scheduled but blocked on
+        // input without making any progress. If every program in the queue is
+        // in that state, then nothing can ever produce new input, and we're
+        // done.
+        let mut num_stalled = 0;
+
+        while let Some((ix, mut execution)) = run_queue.pop_front() {
+            let mut made_progress = false;
             loop {
                 match execution.step()? {
-                    ExecState::Running => {}
+                    ExecState::Running => {
+                        made_progress = true;
+                    }
                     ExecState::DidOutput => {
-                        run_queue.push_back(execution);
+                        num_stalled = 0;
+                        run_queue.push_back((ix, execution));
+                        break;
+                    }
+                    ExecState::AwaitingInput => {
+                        if made_progress {
+                            num_stalled = 0;
+                        }
+                        num_stalled += 1;
+                        run_queue.push_back((ix, execution));
                         break;
                     }
                     ExecState::Halted => {
+                        num_stalled = 0;
+                        outcomes[ix] = ConcurrentOutcome::Halted;
                         break;
                     }
                 }
             }
+
+            if num_stalled > 0 && num_stalled == run_queue.len() {
+                // Everything left is blocked. Outcomes default to stalled.
+                break;
+            }
         }
 
-        Ok(())
+        Ok(outcomes)
+    }
+
+    /// Render the program's code as human-readable instructions, one per line.
+    ///
+    /// Parameters are shown as `[n]` in position mode, `#n` in immediate mode,
+    /// and `@n` in relative mode. Values that don't decode to an instruction
+    /// are shown as `DATA n`, and decoding resumes at the next position.
+    #[allow(dead_code)]
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        let mut pos = 0;
+
+        while pos < self.code.len() {
+            let decoded = from_i64(self.code[pos]).and_then(|op| {
+                let (name, modes, writes) = op.describe();
+                let params = self.code.get(pos + 1..pos + 1 + modes.len())?;
+
+                let mut line = name.to_string();
+                for (i, (mode, &param)) in modes.iter().zip(params).enumerate() {
+                    line.push(' ');
+                    if writes && i == modes.len() - 1 {
+                        line.push_str("-> ");
+                    }
+                    line.push_str(&mode.format_param(param));
+                }
+                Some((line, modes.len() + 1))
+            });
+
+            let (line, width) = decoded.unwrap_or_else(|| (format!("DATA {}", self.code[pos]), 1));
+            out.push_str(&format!("{:04}: {}\n", pos, line));
+            pos += width;
+        }
+
+        out
+    }
+
+    /// Like `run_concurrently`, but also return the values output by each
+    /// program, in the same order as `ios`.
+    pub fn run_concurrently_collecting<T: IO>(
+        &self,
+        ios: &mut [T],
+    ) -> ExecuteResult<(Vec<ConcurrentOutcome>, Vec<Vec<i64>>)> {
+        let mut recording: Vec<RecordingIO<&mut T>> =
+            ios.iter_mut().map(RecordingIO::new).collect();
+
+        let outcomes = self.run_concurrently(&mut recording)?;
+        let outputs = recording.into_iter().map(|io| io.outputs).collect();
+
+        Ok((outcomes, outputs))
     }
 
     pub fn set_address(&mut self, addr: usize, value: i64) {
@@ -247,37 +1418,261 @@ impl Program {
 
     /// Run the program on set of inputs.
     pub fn run_problem2(&self, noun: i64, verb: i64, output_index: usize) -> ExecuteResult<i64> {
+        self.run_with_overrides(&[(1, noun), (2, verb)], output_index)
+    }
+
+    /// Run a copy of the program with the values at some addresses replaced,
+    /// and return the value left at `output_index` after the program halts.
+    ///
+    /// The program itself is not modified.
+    pub fn run_with_overrides(
+        &self,
+        overrides: &[(usize, i64)],
+        output_index: usize,
+    ) -> ExecuteResult<i64> {
+        self.run_with_overrides_in(&mut vec![], overrides, output_index)
+    }
+
+    /// Like `run_with_overrides`, but run in `memory` instead of a newly
+    /// allocated copy of the code, so that hot loops can reuse one buffer.
+    ///
+    /// `memory` is reset to the program's code before running. Afterwards it
+    /// holds the program's final state, or is empty if execution failed.
+    pub fn run_with_overrides_in(
+        &self,
+        memory: &mut Vec<i64>,
+        overrides: &[(usize, i64)],
+        output_index: usize,
+    ) -> ExecuteResult<i64> {
         if self.code.len() <= output_index {
             return ExecuteResult::Err(OutOfBounds(output_index));
         }
 
-        let mut code_copy = self.code.clone();
-        code_copy[1] = noun;
-        code_copy[2] = verb;
+        memory.clone_from(&self.code);
+        for &(addr, value) in overrides {
+            match memory.get_mut(addr) {
+                Some(slot) => *slot = value,
+                None => return ExecuteResult::Err(OutOfBounds(addr)),
+            }
+        }
+
+        let buffer = std::mem::take(memory);
+        *memory = Execution::new(buffer, &mut NoIO).run_to_completion()?;
 
-        let final_state = Execution::new(code_copy, &mut NoIO).run_to_completion()?;
+        Ok(memory[output_index])
+    }
 
-        Ok(final_state[output_index])
+    /// A fresh copy of the program's memory, e.g. to pass to
+    /// `run_with_overrides_in`.
+    pub fn with_memory(&self) -> Vec<i64> {
+        self.code.clone()
     }
 }
 
+/// Programs are comma-separated. Whitespace (including newlines) around each
+/// value is ignored, as are empty fields at the end.
 impl FromStr for Program {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parsed: Result<Vec<i64>, ParseError> = s
-            .trim_end()
-            .split(",")
-            .map(|s| s.parse::<i64>().map_err(|_| ParseError(s.into())))
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+        let end = fields
+            .iter()
+            .rposition(|field| !field.is_empty())
+            .map_or(0, |i| i + 1);
+
+        let parsed: Result<Vec<i64>, ParseError> = fields[..end]
+            .iter()
+            .map(|field| {
+                field
+                    .parse::<i64>()
+                    .map_err(|_| ParseError(field.to_string()))
+            })
             .collect();
 
         Ok(Program::new(parsed?))
     }
 }
 
+/// How a program run by `Program::run_concurrently` finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcurrentOutcome {
+    /// The program executed its exit instruction.
+    Halted,
+    /// The program was still waiting for input when no other program could
+    /// make progress.
+    StalledOnInput,
+}
+
+/// IO that buffers inputs and outputs in queues, for use by `Machine`.
+#[derive(Debug, Default, Clone)]
+struct QueueIO {
+    inputs: VecDeque<i64>,
+    outputs: VecDeque<i64>,
+}
+
+impl IO for QueueIO {
+    fn input(&mut self) -> Option<i64> {
+        self.inputs.pop_front()
+    }
+
+    fn output(&mut self, value: i64) -> Option<()> {
+        self.outputs.push_back(value);
+        Some(())
+    }
+}
+
+/// A running instance of a program that can be driven from the outside.
+///
+/// Unlike `Program::run`, a Machine doesn't need an `IO` implementation.
+/// Inputs are queued with `provide_input`, and execution pauses when the
+/// program needs input that hasn't been provided yet.
+#[derive(Debug)]
+pub struct Machine {
+    execution: Execution<QueueIO>,
+    halted: bool,
+    awaiting_input: bool,
+}
+
+#[allow(dead_code)]
+impl Machine {
+    pub fn new(program: &Program) -> Machine {
+        Machine {
+            execution: Execution::new(program.code.clone(), QueueIO::default()),
+            halted: false,
+            awaiting_input: false,
+        }
+    }
+
+    /// Queue a value to be read by the program's next input instruction.
+    pub fn provide_input(&mut self, value: i64) {
+        self.execution.io.inputs.push_back(value);
+        self.awaiting_input = false;
+    }
+
+    /// Execute a single instruction.
+    ///
+    /// Does nothing if the program has halted, or if it's waiting for input
+    /// that hasn't been provided.
+    pub fn step(&mut self) -> ExecuteResult<()> {
+        if self.halted {
+            return Ok(());
+        }
+
+        match self.execution.step()? {
+            ExecState::Running | ExecState::DidOutput => self.awaiting_input = false,
+            ExecState::AwaitingInput => self.awaiting_input = true,
+            ExecState::Halted => self.halted = true,
+        }
+        Ok(())
+    }
+
+    /// Run until the program produces an output, and return it.
+    ///
+    /// Returns None if the program halts or needs more input first. In the
+    /// latter case, execution can be resumed after calling `provide_input`.
+    pub fn run_until_output(&mut self) -> ExecuteResult<Option<i64>> {
+        loop {
+            if let Some(value) = self.execution.io.outputs.pop_front() {
+                return Ok(Some(value));
+            }
+            if self.halted {
+                return Ok(None);
+            }
+
+            self.step()?;
+            if self.awaiting_input {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Save the program's current memory, instruction pointer, and relative
+    /// base.
+    pub fn snapshot(&self) -> ExecutionState {
+        self.execution.snapshot()
+    }
+
+    /// Roll the program back to a state saved by `snapshot`.
+    ///
+    /// Queued inputs and unread outputs are left as they are.
+    pub fn restore(&mut self, snapshot: &ExecutionState) {
+        self.execution.restore(snapshot);
+        self.halted = false;
+        self.awaiting_input = false;
+    }
+
+    /// Make an independent copy of the machine in its current state,
+    /// including queued inputs and unread outputs.
+    ///
+    /// This is useful for trying several different inputs from the same
+    /// starting point.
+    pub fn fork(&self) -> Machine {
+        Machine {
+            execution: self.execution.clone(),
+            halted: self.halted,
+            awaiting_input: self.awaiting_input,
+        }
+    }
+
+    /// Call `trace` with a line describing each instruction before it's
+    /// executed. Forks of the machine share the callback.
+    pub fn set_trace<F: FnMut(String) + Send + 'static>(&mut self, trace: F) {
+        self.execution.set_trace(trace);
+    }
+
+    /// Has the program executed its exit instruction?
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.execution.steps
+    }
+
+    /// Limit the program to `max_memory` cells of memory, instead of
+    /// `DEFAULT_MAX_MEMORY`.
+    pub fn set_max_memory(&mut self, max_memory: usize) {
+        self.execution.set_max_memory(max_memory);
+    }
+
+    /// Is the program paused waiting for input?
+    pub fn is_awaiting_input(&self) -> bool {
+        self.awaiting_input
+    }
+}
+
 /// A single program execution.
-struct Execution<'a, T: IO> {
-    io: &'a mut T,
+#[derive(Debug, Clone)]
+struct Execution<T: IO> {
+    io: T,
+    state: Vec<i64>,
+    pos: usize,
+    relative_base: i64,
+    /// Number of instructions executed so far.
+    steps: u64,
+    /// Largest number of memory cells the program may use.
+    max_memory: usize,
+    /// Called with a description of each instruction as it's executed.
+    trace: Option<Tracer>,
+}
+
+/// Callback for tracing executed instructions. It's shared so that forked
+/// executions keep tracing.
+#[derive(Clone)]
+struct Tracer(Arc<Mutex<dyn FnMut(String) + Send>>);
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracer")
+    }
+}
+
+/// A saved copy of an execution's memory, instruction pointer, and relative
+/// base, which can be used to roll the execution back to an earlier point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionState {
     state: Vec<i64>,
     pos: usize,
     relative_base: i64,
@@ -286,33 +1681,160 @@ struct Execution<'a, T: IO> {
 enum ExecState {
     Running,
     DidOutput,
+    AwaitingInput,
     Halted,
 }
 
-impl<'a, T: IO> Execution<'a, T> {
-    pub fn new(state: Vec<i64>, io: &'a mut T) -> Execution<'a, T> {
+impl<T: IO> Execution<T> {
+    pub fn new(state: Vec<i64>, io: T) -> Execution<T> {
         Execution {
             state,
             io,
             pos: 0,
             relative_base: 0,
+            steps: 0,
+            max_memory: DEFAULT_MAX_MEMORY,
+            trace: None,
+        }
+    }
+
+    /// Limit the program to `max_memory` cells of memory.
+    pub fn set_max_memory(&mut self, max_memory: usize) {
+        self.max_memory = max_memory;
+    }
+
+    /// Call `trace` with a line describing each instruction before it's
+    /// executed.
+    ///
+    /// Lines look like `0004: ADD [9]=3 @2[7]=4 -> [0]  rb=5`. Each line
+    /// starts with the instruction's position and is followed by its
+    /// parameters, written as in `Program::disassemble`. Relative parameters
+    /// are followed by the address they refer to, and parameters that are
+    /// read are followed by the value read from memory. The line ends with the
+    /// current relative base.
+    ///
+    /// An input instruction that has to wait for input is traced once, when
+    /// it's finally executed.
+    pub fn set_trace<F: FnMut(String) + Send + 'static>(&mut self, trace: F) {
+        self.trace = Some(Tracer(Arc::new(Mutex::new(trace))));
+    }
+
+    /// Save the current memory, instruction pointer, and relative base.
+    pub fn snapshot(&self) -> ExecutionState {
+        ExecutionState {
+            state: self.state.clone(),
+            pos: self.pos,
+            relative_base: self.relative_base,
         }
     }
 
+    /// Roll back to a state saved by `snapshot`. IO is not affected.
+    pub fn restore(&mut self, snapshot: &ExecutionState) {
+        self.state.clone_from(&snapshot.state);
+        self.pos = snapshot.pos;
+        self.relative_base = snapshot.relative_base;
+    }
+
     pub fn run_to_completion(mut self) -> ExecuteResult<Vec<i64>> {
+        self.run_until_halted()?;
+        Ok(self.state)
+    }
+
+    fn run_until_halted(&mut self) -> ExecuteResult<()> {
+        self.run_until_halted_with_limit(None)
+    }
+
+    fn run_until_halted_with_limit(&mut self, max_steps: Option<usize>) -> ExecuteResult<()> {
         loop {
+            if let Some(limit) = max_steps {
+                if self.steps >= limit as u64 {
+                    return Err(StepLimitExceeded(limit));
+                }
+            }
             match self.step()? {
                 ExecState::Halted => {
                     break;
                 }
+                ExecState::AwaitingInput => return Err(InputError),
                 _ => {}
             }
         }
-        Ok(self.state)
+        Ok(())
     }
 
+    /// Execute a single instruction, keeping count of completed instructions.
     pub fn step(&mut self) -> ExecuteResult<ExecState> {
-        let code = self.state[self.pos];
+        // Describe the instruction before executing it, since executing it
+        // can change its parameters.
+        let line = self.trace.as_ref().and_then(|_| self.trace_line());
+
+        let state = self.execute_instruction();
+        if let (Some(Tracer(trace)), Some(line)) = (&self.trace, line) {
+            match state {
+                Ok(ExecState::AwaitingInput) => {}
+                _ => (trace.lock().unwrap())(line),
+            }
+        }
+
+        let state = state?;
+        match state {
+            ExecState::AwaitingInput => {}
+            _ => self.steps += 1,
+        }
+        Ok(state)
+    }
+
+    /// Describe the instruction at `pos` for `set_trace`, without changing
+    /// any state. Returns None if `pos` is past the end of memory.
+    fn trace_line(&self) -> Option<String> {
+        let code = *self.state.get(self.pos)?;
+        let mut line = format!("{:04}: ", self.pos);
+
+        let (name, modes, writes) = match from_i64(code) {
+            Some(op) => op.describe(),
+            None => {
+                line.push_str(&format!("DATA {}", code));
+                return Some(line);
+            }
+        };
+
+        line.push_str(name);
+        for (i, &mode) in modes.iter().enumerate() {
+            let param = match self.state.get(self.pos + 1 + i) {
+                Some(&param) => param,
+                None => break,
+            };
+            let is_write = writes && i == modes.len() - 1;
+
+            line.push(' ');
+            if is_write {
+                line.push_str("-> ");
+            }
+            line.push_str(&mode.format_param(param));
+
+            let address = match mode {
+                ParameterMode::Immediate => continue,
+                _ => match self.as_address(param, mode) {
+                    Ok(address) => address,
+                    Err(_) => continue,
+                },
+            };
+            if let ParameterMode::Relative = mode {
+                line.push_str(&format!("[{}]", address));
+            }
+            if !is_write {
+                // Memory past the end of the program reads as zero.
+                let value = self.state.get(address).copied().unwrap_or(0);
+                line.push_str(&format!("={}", value));
+            }
+        }
+
+        line.push_str(&format!("  rb={}", self.relative_base));
+        Some(line)
+    }
+
+    fn execute_instruction(&mut self) -> ExecuteResult<ExecState> {
+        let code = self.fetch(self.pos)?;
         let op = from_i64(code);
         match op {
             Some(Op::Add(lhs_mode, rhs_mode, dest_mode)) => {
@@ -334,7 +1856,8 @@ impl<'a, T: IO> Execution<'a, T> {
             Some(Op::JumpIfTrue(test_mode, target_mode)) => {
                 let test = self.do_read(self.pos + 1, test_mode)?;
                 if test != 0 {
-                    self.pos = self.do_read(self.pos + 2, target_mode)? as usize;
+                    let target = self.do_read(self.pos + 2, target_mode)?;
+                    self.pos = self.jump_target(target)?;
                 } else {
                     self.pos += 3;
                 }
@@ -342,7 +1865,8 @@ impl<'a, T: IO> Execution<'a, T> {
             Some(Op::JumpIfFalse(test_mode, target_mode)) => {
                 let test = self.do_read(self.pos + 1, test_mode)?;
                 if test == 0 {
-                    self.pos = self.do_read(self.pos + 2, target_mode)? as usize;
+                    let target = self.do_read(self.pos + 2, target_mode)?;
+                    self.pos = self.jump_target(target)?;
                 } else {
                     self.pos += 3;
                 }
@@ -352,7 +1876,8 @@ impl<'a, T: IO> Execution<'a, T> {
                     Some(value) => {
                         self.do_write(self.pos + 1, mode, value)?;
                     }
-                    None => return Err(InputError),
+                    // Leave pos unchanged so that the input can be retried.
+                    None => return Ok(ExecState::AwaitingInput),
                 }
                 self.pos += 2;
             }
@@ -370,7 +1895,10 @@ impl<'a, T: IO> Execution<'a, T> {
             }
             Some(Op::AdjustRelativeBase(mode)) => {
                 let value = self.do_read(self.pos + 1, mode)?;
-                self.relative_base += value;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(value)
+                    .ok_or(RelativeBaseOverflow { pos: self.pos })?;
                 self.pos += 2;
             }
             None => {
@@ -401,45 +1929,81 @@ impl<'a, T: IO> Execution<'a, T> {
     }
 
     fn do_read(&mut self, pos: usize, mode: ParameterMode) -> ExecuteResult<i64> {
-        let param = self.state[pos];
+        let param = self.fetch(pos)?;
         match mode {
             ParameterMode::Position | ParameterMode::Relative => {
-                Ok(self.read_raw(self.as_address(param, mode)?))
+                self.read_raw(self.as_address(param, mode)?)
             }
             ParameterMode::Immediate => Ok(param),
         }
     }
 
     fn do_write(&mut self, pos: usize, mode: ParameterMode, value: i64) -> ExecuteResult<()> {
-        let param = self.state[pos];
+        let param = self.fetch(pos)?;
         let dest = self.as_address(param, mode)?;
 
-        self.write_raw(dest, value);
+        self.write_raw(dest, value)
+    }
+
+    /// Read an opcode or parameter from the program, failing with
+    /// `OutOfBounds` if the program ends before `pos`.
+    fn fetch(&self, pos: usize) -> ExecuteResult<i64> {
+        self.state.get(pos).copied().ok_or(OutOfBounds(pos))
+    }
 
-        Ok(())
+    /// Convert a jump target to a position, failing if it's negative or past
+    /// the end of memory.
+    fn jump_target(&self, value: i64) -> ExecuteResult<usize> {
+        let target = self.checked_address(value)?;
+        if target >= self.state.len() {
+            return Err(OutOfBounds(target));
+        }
+        Ok(target)
     }
 
-    fn read_raw(&mut self, ix: usize) -> i64 {
-        self.ensure_capacity(ix);
-        self.state[ix]
+    fn read_raw(&mut self, ix: usize) -> ExecuteResult<i64> {
+        self.ensure_capacity(ix)?;
+        Ok(self.state[ix])
     }
 
-    fn write_raw(&mut self, ix: usize, value: i64) {
-        self.ensure_capacity(ix);
+    fn write_raw(&mut self, ix: usize, value: i64) -> ExecuteResult<()> {
+        self.ensure_capacity(ix)?;
         self.state[ix] = value;
+        Ok(())
     }
 
     fn as_address(&self, param: i64, mode: ParameterMode) -> ExecuteResult<usize> {
         match mode {
-            ParameterMode::Position => Ok(param as usize),
-            ParameterMode::Relative => Ok((param + self.relative_base) as usize),
-            ParameterMode::Immediate => panic!("Can't take address of immediate"),
+            ParameterMode::Position => self.checked_address(param),
+            // Saturate so that an extreme relative base fails the address
+            // check instead of overflowing.
+            ParameterMode::Relative => {
+                self.checked_address(param.saturating_add(self.relative_base))
+            }
+            ParameterMode::Immediate => Err(ImmediateWriteTarget { pos: self.pos }),
         }
     }
 
-    fn ensure_capacity(&mut self, ix: usize) {
+    fn checked_address(&self, value: i64) -> ExecuteResult<usize> {
+        if value < 0 {
+            Err(NegativeAddress {
+                value,
+                pos: self.pos,
+            })
+        } else {
+            Ok(value as usize)
+        }
+    }
+
+    /// Grow memory to include `ix`, failing with `OutOfBounds` if that would
+    /// make memory larger than `max_memory`.
+    fn ensure_capacity(&mut self, ix: usize) -> ExecuteResult<()> {
+        if ix >= self.max_memory {
+            return Err(OutOfBounds(ix));
+        }
         if ix >= self.state.len() {
             self.state.resize(ix + 1, 0);
         }
+        Ok(())
     }
 }
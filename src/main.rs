@@ -1,5 +1,8 @@
 use std::env;
+use std::time::{Duration, Instant};
 
+mod explore;
+mod graph;
 mod grid;
 mod intcode;
 mod utils;
@@ -14,7 +17,14 @@ mod problem14;
 mod problem15;
 mod problem16;
 mod problem17;
+mod problem18;
+mod problem19;
 mod problem2;
+mod problem20;
+mod problem21;
+mod problem22;
+mod problem23;
+mod problem24;
 mod problem3;
 mod problem4;
 mod problem5;
@@ -23,30 +33,121 @@ mod problem7;
 mod problem8;
 mod problem9;
 
+use utils::Part;
+
+/// Problems that have been solved so far, in the order `all` runs them.
+const PROBLEMS: &[u32] = &[
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+];
+
+/// Usage: aoc2019 [--time] <problem|all> [part]
+///
+/// Timing is also enabled by setting the AOC_TIME environment variable.
+/// Running "all" runs every problem in order and prints a table of timings.
 fn main() -> utils::ProblemResult<()> {
-    let args: Vec<String> = env::args().collect();
-    let num_args = args.len() - 1;
-    match num_args {
-        1 => match args[1].as_ref() {
-            "1" => problem1::run(),
-            "2" => problem2::run(),
-            "3" => problem3::run(),
-            "4" => problem4::run(),
-            "5" => problem5::run(),
-            "6" => problem6::run(),
-            "7" => problem7::run(),
-            "8" => problem8::run(),
-            "9" => problem9::run(),
-            "10" => problem10::run(),
-            "11" => problem11::run(),
-            "12" => problem12::run(),
-            "13" => problem13::run(),
-            "14" => problem14::run(),
-            "15" => problem15::run(),
-            "16" => problem16::run(),
-            "17" => problem17::run(),
-            _ => utils::bail(&format!("Unknown problem: {}", args[1])),
-        },
-        _ => utils::bail(&format!("Expected 1 arg, got {}", num_args)),
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let time_flag = args.iter().position(|a| a == "--time");
+    if let Some(i) = time_flag {
+        args.remove(i);
+    }
+    let time = time_flag.is_some() || env::var_os("AOC_TIME").is_some();
+
+    let part = match args.len() {
+        1 => Part::Both,
+        2 => args[1].parse::<Part>()?,
+        n => return utils::bail(format!("Expected 1 or 2 args, got {}", n)),
+    };
+
+    if args[0] == "all" {
+        return run_all(part);
     }
+
+    if time {
+        let (result, elapsed) = timed(|| run_problem(&args[0], part));
+        println!("\nProblem {} took {} ms", args[0], elapsed.as_millis());
+        result
+    } else {
+        run_problem(&args[0], part)
+    }
+}
+
+fn run_problem(problem: &str, part: Part) -> utils::ProblemResult<()> {
+    match problem {
+        "1" => problem1::solve(part).map(print_answers),
+        "2" => problem2::solve(part).map(print_answers),
+        "3" => problem3::run(part),
+        "4" => problem4::run(part),
+        "5" => problem5::run(part),
+        "6" => problem6::run(part),
+        "7" => problem7::run(part),
+        "8" => problem8::run(part),
+        "9" => problem9::run(part),
+        "10" => problem10::run(part),
+        "11" => problem11::run(part),
+        "12" => problem12::run(part),
+        "13" => problem13::run(part),
+        "14" => problem14::run(part),
+        "15" => problem15::run(part),
+        "16" => problem16::run(part),
+        "17" => problem17::run(part),
+        "18" => problem18::run(part),
+        "19" => problem19::run(part),
+        "20" => problem20::run(part),
+        "21" => problem21::run(part),
+        "22" => problem22::run(part),
+        "23" => problem23::run(part),
+        "24" => problem24::run(part),
+        _ => utils::bail(format!("Unknown problem: {}", problem)),
+    }
+}
+
+/// Run every problem in order, then print how long each one took.
+///
+/// A failing problem (e.g. one whose input file is missing) doesn't stop the
+/// others from running.
+fn run_all(part: Part) -> utils::ProblemResult<()> {
+    let mut timings: Vec<(u32, Option<Duration>)> = vec![];
+    for &n in PROBLEMS.iter() {
+        let (result, elapsed) = timed(|| run_problem(&n.to_string(), part));
+        match result {
+            Ok(()) => timings.push((n, Some(elapsed))),
+            Err(e) => {
+                println!("Problem {} failed: {}", n, e);
+                timings.push((n, None));
+            }
+        }
+        println!();
+    }
+
+    println!("Problem | Time (ms)");
+    println!("--------+----------");
+    for (n, elapsed) in timings.iter() {
+        match elapsed {
+            Some(elapsed) => println!("{:>7} | {:>9}", n, elapsed.as_millis()),
+            None => println!("{:>7} | {:>9}", n, "failed"),
+        }
+    }
+    let total: Duration = timings.iter().filter_map(|(_, elapsed)| *elapsed).sum();
+    println!("  Total | {:>9}", total.as_millis());
+
+    let num_failed = timings
+        .iter()
+        .filter(|(_, elapsed)| elapsed.is_none())
+        .count();
+    if num_failed > 0 {
+        return utils::bail(format!("{} problem(s) failed", num_failed));
+    }
+
+    Ok(())
+}
+
+fn print_answers(answers: utils::ProblemAnswers) {
+    println!("{}", answers);
+}
+
+fn timed<T, F: FnOnce() -> T>(f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
 }
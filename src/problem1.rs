@@ -66,32 +66,29 @@
 /// spacecraft when also taking into account the mass of the added fuel?
 /// (Calculate the fuel requirements for each module separately, then add them
 /// all up at the end.)
-use std::fs;
-use std::num;
-use std::path::Path;
+use std::convert::TryFrom;
 
-use crate::utils::ProblemResult;
+use crate::utils::{bail, lines_for_problem, Part, ProblemAnswers, ProblemResult};
 
-pub fn run() -> ProblemResult<()> {
-    let here = Path::new(file!()).parent().unwrap();
-    let input_path = here.join("inputs/problem1_input.txt");
+/// Total fuel required for the modules in the input, for each part.
+pub fn solve(part: Part) -> ProblemResult<ProblemAnswers> {
+    let masses: Vec<u64> = lines_for_problem(1)?;
+    let mut answers = ProblemAnswers::default();
 
-    let masses = read_masses(&input_path)?;
+    if part.includes(Part::One) {
+        let basic_fuels = masses.iter().cloned().map(basic_fuel_for_mass);
+        let total_basic_fuel: u64 = basic_fuels.sum();
+        answers.part1 = Some(total_basic_fuel.to_string());
+    }
 
-    println!("Problem 1:");
-    println!("==========");
-
-    // Part 1
-    let basic_fuels = masses.iter().cloned().map(basic_fuel_for_mass);
-    let total_basic_fuel: u64 = basic_fuels.sum();
-    println!("Total Fuel Required for Part 1: {}", total_basic_fuel);
-
-    // Part 2
-    let fuels = masses.into_iter().map(fuel_for_mass);
-    let total_fuel: u64 = fuels.sum();
-    println!("Total Fuel Required for Part 2: {}", total_fuel);
+    if part.includes(Part::Two) {
+        match total_fuel_checked(&masses) {
+            Some(total_fuel) => answers.part2 = Some(total_fuel.to_string()),
+            None => return bail("Total fuel doesn't fit in a u64"),
+        }
+    }
 
-    Ok(())
+    Ok(answers)
 }
 
 fn basic_fuel_for_mass(mass: u64) -> u64 {
@@ -99,29 +96,85 @@ fn basic_fuel_for_mass(mass: u64) -> u64 {
     (mass / 3).checked_sub(2).unwrap_or(0)
 }
 
-fn fuel_for_mass(mass: u64) -> u64 {
-    let initial_fuel = basic_fuel_for_mass(mass);
-
-    // Iterator that iteratively calls next_fuel on the previous result until
-    // None is returned.
-    let incremental_fuels = std::iter::successors(Some(initial_fuel), |mass: &u64| {
-        match basic_fuel_for_mass(*mass) {
-            0 => None,
-            n => Some(n),
-        }
-    });
-
-    return incremental_fuels.sum();
+/// Total fuel for a module, including the fuel needed to carry its fuel.
+#[allow(dead_code)]
+fn total_fuel_for_mass(mass: u64) -> u64 {
+    // The fuel is less than half the mass, so it always fits in a u64.
+    fuel_for_mass_checked(mass.into()).unwrap()
 }
 
-fn read_masses(path: &Path) -> ProblemResult<Vec<u64>> {
-    let file_content = fs::read_to_string(path)?;
+/// Total fuel for a module of any mass, including the fuel needed to carry
+/// its fuel.
+///
+/// Returns None if the fuel needed doesn't fit in a u64.
+fn fuel_for_mass_checked(mass: u128) -> Option<u64> {
+    let mut total: u128 = 0;
+    let mut fuel = (mass / 3).saturating_sub(2);
+    while fuel > 0 {
+        total += fuel;
+        fuel = (fuel / 3).saturating_sub(2);
+    }
+    u64::try_from(total).ok()
+}
 
-    let masses: Result<Vec<u64>, num::ParseIntError> = file_content
-        .lines()
-        .map(|line| line.parse::<u64>())
-        .collect();
+/// Total fuel for all modules, or None if it overflows a u64.
+fn total_fuel_checked(masses: &[u64]) -> Option<u64> {
+    masses.iter().try_fold(0u64, |total, &mass| {
+        total.checked_add(fuel_for_mass_checked(mass.into())?)
+    })
+}
 
-    // Convert parse error into dynamic error.
-    masses.map_err(|e| e.into())
+mod tests {
+    #[test]
+    fn test_basic_fuel_for_mass() {
+        use super::basic_fuel_for_mass;
+
+        assert_eq!(basic_fuel_for_mass(12), 2);
+        assert_eq!(basic_fuel_for_mass(14), 2);
+        assert_eq!(basic_fuel_for_mass(1969), 654);
+        assert_eq!(basic_fuel_for_mass(100756), 33583);
+    }
+
+    #[test]
+    fn test_total_fuel_for_mass() {
+        use super::total_fuel_for_mass;
+
+        assert_eq!(total_fuel_for_mass(2), 0);
+        assert_eq!(total_fuel_for_mass(14), 2);
+        assert_eq!(total_fuel_for_mass(1969), 966);
+        assert_eq!(total_fuel_for_mass(100756), 50346);
+    }
+
+    #[test]
+    fn test_fuel_for_mass_checked() {
+        use super::{fuel_for_mass_checked, total_fuel_checked};
+
+        assert_eq!(fuel_for_mass_checked(14), Some(2));
+        assert_eq!(fuel_for_mass_checked(1969), Some(966));
+        assert_eq!(fuel_for_mass_checked(100756), Some(50346));
+        assert_eq!(
+            fuel_for_mass_checked(u64::MAX.into()),
+            Some(9223372036854775669)
+        );
+
+        // Fuel is a bit under half the mass, so this needs more than a u64.
+        assert_eq!(fuel_for_mass_checked(u128::from(u64::MAX) * 3), None);
+
+        assert_eq!(total_fuel_checked(&[1969, 100756]), Some(966 + 50346));
+        assert_eq!(total_fuel_checked(&[u64::MAX, u64::MAX, u64::MAX]), None);
+    }
+
+    #[test]
+    fn test_solve() {
+        use super::solve;
+        use crate::utils::Part;
+
+        let answers = solve(Part::Both).unwrap();
+        assert_eq!(answers.part1.as_deref(), Some("3328306"));
+        assert_eq!(answers.part2.as_deref(), Some("4989588"));
+
+        let answers = solve(Part::Two).unwrap();
+        assert_eq!(answers.part1, None);
+        assert_eq!(answers.part2.as_deref(), Some("4989588"));
+    }
 }
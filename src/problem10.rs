@@ -212,9 +212,10 @@ example, 8,2 becomes 802.)
 
  */
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::utils::{ProblemInput, ProblemResult, SimpleError};
+use crate::utils::math::gcd;
+use crate::utils::{bail, Part, ProblemInput, ProblemResult, SimpleError};
 
 use std::convert::TryInto;
 use std::str::FromStr;
@@ -227,7 +228,7 @@ enum Space {
 
 type Coord = (usize, usize);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct AsteroidMap {
     asteroids: HashSet<Coord>,
     pub nrows: usize,
@@ -243,40 +244,151 @@ impl AsteroidMap {
         }
     }
 
+    /// Iterate over the coordinates of all asteroids on the map.
+    #[allow(dead_code)]
+    pub fn asteroids(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.asteroids.iter().copied()
+    }
+
+    /// Check whether there's an asteroid at `c`.
+    pub fn contains(&self, c: Coord) -> bool {
+        self.asteroids.contains(&c)
+    }
+
+    /// Find the asteroid that can see the most other asteroids, and the
+    /// number it can see.
+    ///
+    /// Ties go to the first asteroid in reading order, i.e. the one with the
+    /// smallest `(y, x)`.
     pub fn best_station_coord(&self) -> (Coord, u64) {
         self.asteroids
             .iter()
-            .map(|&c| (c, self.num_visible_from(c)))
-            .max_by_key(|(_c, v)| *v)
+            .map(|&c| (c, self.count_visible_from(c)))
+            .max_by(|&((x1, y1), v1), &((x2, y2), v2)| {
+                v1.cmp(&v2).then_with(|| (y2, x2).cmp(&(y1, x1)))
+            })
             .unwrap()
     }
 
-    pub fn destruction_order(mut self, coord: Coord) -> Vec<Coord> {
-        let directions = all_directions(self.nrows, self.ncols);
+    /// Order in which asteroids are destroyed by a laser at `coord` that
+    /// starts pointing up and rotates clockwise.
+    pub fn destruction_order(&self, coord: Coord) -> Vec<Coord> {
+        // Group asteroids by direction from the station, then sort each group
+        // by distance.
+        let mut by_direction: HashMap<(i64, i64), Vec<(i64, Coord)>> = HashMap::new();
+        for &c in self.asteroids.iter().filter(|&&c| c != coord) {
+            let (direction, steps) = reduced_offset(coord, c);
+            by_direction.entry(direction).or_default().push((steps, c));
+        }
 
-        let mut out = Vec::new();
+        // Sort directions clockwise, starting from straight up.
+        let mut directions: Vec<(i64, i64)> = by_direction.keys().copied().collect();
+        directions.sort_by(|&a, &b| clockwise_angle(a).partial_cmp(&clockwise_angle(b)).unwrap());
 
-        // > 1 because we never destroy ``coord``.
-        while self.asteroids.len() > 1 {
-            for dir in directions.iter() {
-                match self.cast_ray(coord, *dir) {
-                    Some(destroyed) => {
-                        out.push(destroyed);
-                        self.asteroids.remove(&destroyed);
-                    }
-                    None => {}
-                }
+        let groups: Vec<Vec<(i64, Coord)>> = directions
+            .iter()
+            .map(|d| {
+                let mut group = by_direction.remove(d).unwrap();
+                group.sort();
+                group
+            })
+            .collect();
+
+        // Each rotation destroys the nearest remaining asteroid in each
+        // direction.
+        let rotations = groups.iter().map(Vec::len).max().unwrap_or(0);
+        (0..rotations)
+            .flat_map(|i| groups.iter().filter_map(move |g| g.get(i)))
+            .map(|&(_, c)| c)
+            .collect()
+    }
+
+    /// Find the `n`th asteroid (counting from 1) destroyed by a laser at
+    /// `coord`, or None if fewer than `n` asteroids are destroyed.
+    pub fn nth_vaporized(&self, coord: Coord, n: usize) -> Option<Coord> {
+        if n == 0 {
+            return None;
+        }
+        self.destruction_order(coord).get(n - 1).copied()
+    }
+
+    /// Render the map with the station at `coord` marked as `X` and the first
+    /// `upto` destroyed asteroids marked with the order in which they were
+    /// destroyed. As in the puzzle's figures, labels run from 1 to 9 and then
+    /// start again from 1.
+    #[allow(dead_code)]
+    pub fn render_destruction(&self, coord: Coord, upto: usize) -> String {
+        let order = self.destruction_order(coord);
+        let labels: HashMap<Coord, char> = order
+            .into_iter()
+            .take(upto)
+            .enumerate()
+            .map(|(i, c)| (c, (b'1' + (i % 9) as u8) as char))
+            .collect();
+
+        let mut out = String::new();
+        for y in 0..self.nrows {
+            for x in 0..self.ncols {
+                let c = if (x, y) == coord {
+                    'X'
+                } else if let Some(&label) = labels.get(&(x, y)) {
+                    label
+                } else if self.contains((x, y)) {
+                    '#'
+                } else {
+                    '.'
+                };
+                out.push(c);
             }
+            out.push('\n');
         }
+        out
+    }
 
+    /// Render the map with each asteroid replaced by the number of other
+    /// asteroids visible from it, as in the puzzle's figures. Counts above 9
+    /// are drawn as `+`.
+    #[allow(dead_code)]
+    pub fn visibility_map(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.nrows {
+            for x in 0..self.ncols {
+                let c = if self.contains((x, y)) {
+                    match self.num_visible_from((x, y)) {
+                        n @ 0..=9 => (b'0' + n as u8) as char,
+                        _ => '+',
+                    }
+                } else {
+                    '.'
+                };
+                out.push(c);
+            }
+            out.push('\n');
+        }
         out
     }
 
     /// Count number of asteroids visible from a cell.
+    ///
+    /// Exactly one asteroid is visible in each direction that has any
+    /// asteroids, so this is the number of distinct directions to the other
+    /// asteroids.
+    fn count_visible_from(&self, coord: Coord) -> u64 {
+        let directions: HashSet<(i64, i64)> = self
+            .asteroids
+            .iter()
+            .filter(|&&c| c != coord)
+            .map(|&c| reduced_offset(coord, c).0)
+            .collect();
+        directions.len() as u64
+    }
+
+    /// Like `count_visible_from`, but casts a ray in every possible direction
+    /// to find the visible asteroids.
     fn num_visible_from(&self, coord: Coord) -> u64 {
         let mut count = 0;
 
-        for direction in all_directions(self.nrows, self.ncols) {
+        for direction in clockwise_directions(self.nrows, self.ncols) {
             if let Some(_) = self.cast_ray(coord, direction) {
                 count += 1;
             }
@@ -305,7 +417,7 @@ impl AsteroidMap {
         if x >= self.ncols || y >= self.nrows {
             None
         } else {
-            if self.asteroids.contains(&(x, y)) {
+            if self.contains((x, y)) {
                 Some(Space::Asteroid)
             } else {
                 Some(Space::Empty)
@@ -322,15 +434,15 @@ impl FromStr for AsteroidMap {
             .trim()
             .lines()
             .map(|line| {
-                line.bytes()
+                line.chars()
                     .map(|c| match c {
-                        b'#' => Space::Asteroid,
-                        b'.' => Space::Empty,
-                        _ => panic!("Bad asteroid character: {}", c),
+                        '#' => Ok(Space::Asteroid),
+                        '.' => Ok(Space::Empty),
+                        _ => Err(SimpleError(format!("Bad asteroid character: {:?}", c))),
                     })
                     .collect()
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         let lengths = lines.iter().map(|v| v.len()).collect::<HashSet<usize>>();
 
@@ -359,99 +471,295 @@ impl FromStr for AsteroidMap {
     }
 }
 
-fn all_directions(nrows: usize, ncols: usize) -> Vec<(i64, i64)> {
-    // All possible positive ratios that can be expressed on a grid of shape
-    // (nrows, ncols).
-    let mut positive_ratios: Vec<(i64, i64)> = (0..ncols)
-        .flat_map(|dx| (0..nrows).map(move |dy| (dx, dy)))
-        .filter_map(|(dx, dy)| match (dx, dy) {
-            (0, 0) => None,
-            (0, _) => Some((0, 1)),
-            (_, 0) => Some((1, 0)),
-            (n, m) => {
-                match gcd(n, m) {
-                    1 => Some((n, m)),
-                    // Filter out non-relatively-prime pairs.
-                    _ => None,
-                }
-            }
-        })
-        .map(|(dx, dy)| (dx as i64, dy as i64))
-        .collect();
+/// Offset from `from` to `to`, as a direction and a number of steps in that
+/// direction.
+///
+/// Dividing the offset by the gcd of its components gives a unique
+/// representative for each direction.
+fn reduced_offset(from: Coord, to: Coord) -> ((i64, i64), i64) {
+    let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+    let g = gcd(dx.unsigned_abs(), dy.unsigned_abs()) as i64;
+    ((dx / g, dy / g), g)
+}
 
-    positive_ratios.sort_by(|&(dx1, dy1), &(dx2, dy2)| {
-        let slope1 = (dy1 as f64) / (dx1 as f64);
-        let slope2 = (dy2 as f64) / (dx2 as f64);
-        slope1.partial_cmp(&slope2).unwrap()
-    });
-    positive_ratios.dedup();
-
-    // The y coordinate is negated from what you'd normally expect because
-    // that's the coordinate system given in the problem. The first and third
-    // quadrants are reversed because positive_ratios are sorted by
-    // **increasing** absolute value of slope, but in quadrants 1 and 3, we
-    // want to go in decreasing order.
-    let first = positive_ratios
-        .iter()
-        .copied()
-        .rev()
-        .map(|(dx, dy)| (dx, -dy));
-    let second = positive_ratios.iter().copied();
-    let third = positive_ratios
-        .iter()
-        .rev()
-        .copied()
-        .map(|(dx, dy)| (-dx, dy));
-    let fourth = positive_ratios.iter().copied().map(|(dx, dy)| (-dx, -dy));
-
-    let mut out: Vec<(i64, i64)> = first.chain(second).chain(third).chain(fourth).collect();
-
-    // XXX: This is super ugly. Remove duplicates due to first and last elements being the same.
-    out.dedup();
-    out.pop();
-    out
+/// Angle of the direction `(dx, dy)` clockwise from straight up, in
+/// `[0, 2pi)`. y increases downward, so "up" is negative dy.
+fn clockwise_angle((dx, dy): (i64, i64)) -> f64 {
+    let theta = (dx as f64).atan2(-dy as f64);
+    if theta < 0.0 {
+        theta + 2.0 * std::f64::consts::PI
+    } else {
+        theta
+    }
 }
 
-fn gcd(n: usize, m: usize) -> usize {
-    use std::cmp::Ordering;
+/// Iterate over every direction that can be taken between two cells of a
+/// grid of shape (nrows, ncols), sweeping clockwise from straight up.
+///
+/// Each direction is yielded once, as the offset with the smallest whole
+/// components.
+fn clockwise_directions(nrows: usize, ncols: usize) -> impl Iterator<Item = (i64, i64)> {
+    let (max_dx, max_dy) = (ncols as i64 - 1, nrows as i64 - 1);
+
+    // Only offsets whose components are relatively prime are kept, so each
+    // direction appears exactly once.
+    let mut directions: Vec<(i64, i64)> = (-max_dx..=max_dx)
+        .flat_map(|dx| (-max_dy..=max_dy).map(move |dy| (dx, dy)))
+        .filter(|&(dx, dy)| gcd(dx.unsigned_abs(), dy.unsigned_abs()) == 1)
+        .collect();
 
-    match n.cmp(&m) {
-        Ordering::Equal => n,
-        Ordering::Greater => gcd(n - m, m),
-        Ordering::Less => gcd(n, m - n),
-    }
+    directions.sort_by(|&a, &b| clockwise_angle(a).partial_cmp(&clockwise_angle(b)).unwrap());
+    directions.into_iter()
 }
 
 mod tests {
     #[test]
-    fn test_gcd() {
-        use super::gcd;
+    fn test_parse() {
+        use super::{AsteroidMap, Coord};
+
+        let map: AsteroidMap = ".#..#\n.....\n#####\n....#\n...##".parse().unwrap();
+        assert_eq!(map.nrows, 5);
+        assert_eq!(map.ncols, 5);
+
+        let mut asteroids: Vec<Coord> = map.asteroids().collect();
+        asteroids.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(
+            asteroids,
+            vec![
+                (1, 0),
+                (4, 0),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (3, 2),
+                (4, 2),
+                (4, 3),
+                (3, 4),
+                (4, 4),
+            ]
+        );
+
+        assert!(map.contains((3, 4)));
+        assert!(!map.contains((0, 0)));
+    }
+
+    #[test]
+    fn test_clockwise_directions() {
+        use super::clockwise_directions;
+        use std::collections::HashSet;
+
+        let directions: Vec<(i64, i64)> = clockwise_directions(3, 3).collect();
+        assert_eq!(
+            directions[..6],
+            [(0, -1), (1, -2), (1, -1), (2, -1), (1, 0), (2, 1)]
+        );
+        assert_eq!(directions.last(), Some(&(-1, -2)));
+
+        // Every pair of relatively prime offsets in [-2, 2], once each.
+        let unique: HashSet<(i64, i64)> = directions.iter().copied().collect();
+        assert_eq!(unique.len(), directions.len());
+        assert_eq!(directions.len(), 16);
+    }
+
+    #[test]
+    fn test_count_visible_matches_ray_casting() {
+        use super::AsteroidMap;
+
+        let maps = [
+            (".#..#\n.....\n#####\n....#\n...##", (3, 4), 8),
+            (
+                "......#.#.
+#..#.#....
+..#######.
+.#.#.###..
+.#..#.....
+..#....#.#
+#..#....#.
+.##.#..###
+##...#..#.
+.#....####",
+                (5, 8),
+                33,
+            ),
+        ];
+
+        for &(input, best, count) in maps.iter() {
+            let map: AsteroidMap = input.parse().unwrap();
+            for c in map.asteroids() {
+                assert_eq!(map.count_visible_from(c), map.num_visible_from(c));
+            }
+            assert_eq!(map.best_station_coord(), (best, count));
+        }
+    }
+
+    #[test]
+    fn test_best_station_ties() {
+        use super::AsteroidMap;
+
+        // Every asteroid can see all three others.
+        for _ in 0..10 {
+            let map: AsteroidMap = ".#.\n#.#\n.#.".parse().unwrap();
+            assert_eq!(map.best_station_coord(), ((1, 0), 3));
+        }
+
+        // (0, 1), (2, 1) and (1, 2) can each see all four others.
+        let map: AsteroidMap = ".#.\n#.#\n.#.\n.#.".parse().unwrap();
+        assert_eq!(map.best_station_coord(), ((0, 1), 4));
+    }
+
+    #[test]
+    fn test_parse_bad_character() {
+        use super::AsteroidMap;
 
-        assert_eq!(gcd(1, 1), 1);
-        assert_eq!(gcd(2, 2), 2);
-        assert_eq!(gcd(100, 100), 100);
+        let err = ".#..#\n..?..\n#####".parse::<AsteroidMap>().unwrap_err();
+        assert_eq!(err.to_string(), "Bad asteroid character: '?'");
+    }
+
+    #[test]
+    fn test_visibility_map() {
+        use super::AsteroidMap;
 
-        assert_eq!(gcd(2, 4), 2);
-        assert_eq!(gcd(4, 2), 2);
+        let map: AsteroidMap = ".#..#\n.....\n#####\n....#\n...##".parse().unwrap();
+        assert_eq!(map.visibility_map(), ".7..7\n.....\n67775\n....7\n...87\n");
 
-        assert_eq!(gcd(3, 6), 3);
-        assert_eq!(gcd(6, 3), 3);
+        // In a 4x4 block, each corner sees 9 others. Along the edges and the
+        // diagonal, only the nearest asteroid is visible, which hides 6 of
+        // the 15. Every other asteroid sees more.
+        let map: AsteroidMap = "####\n####\n####\n####".parse().unwrap();
+        assert_eq!(map.visibility_map(), "9++9\n++++\n++++\n9++9\n");
+    }
 
-        assert_eq!(gcd(24, 18), 6);
-        assert_eq!(gcd(18, 24), 6);
+    #[test]
+    fn test_too_few_asteroids() {
+        use super::{find_200th_vaporized, AsteroidMap};
+
+        let map: AsteroidMap = ".#..#\n.....\n#####\n....#\n...##".parse().unwrap();
+        let err = find_200th_vaporized(&map, (3, 4)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Map has only 9 asteroids other than the station. Can't vaporize 200 of them."
+        );
+    }
+
+    #[test]
+    fn test_200th_vaporized() {
+        use super::{find_200th_vaporized, AsteroidMap};
+
+        let map: AsteroidMap = "
+.#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##"
+            .parse()
+            .unwrap();
+
+        assert_eq!(map.best_station_coord(), ((11, 13), 210));
+        let order = map.destruction_order((11, 13));
+        assert_eq!(order.len(), 299);
+        assert_eq!(order[0], (11, 12));
+        assert_eq!(order[1], (12, 1));
+        assert_eq!(order[2], (12, 2));
+        assert_eq!(order[9], (12, 8));
+        assert_eq!(order[19], (16, 0));
+        assert_eq!(order[49], (16, 9));
+        assert_eq!(order[99], (10, 16));
+        assert_eq!(order[198], (9, 6));
+        assert_eq!(order[200], (10, 9));
+        assert_eq!(order[298], (11, 1));
+
+        assert_eq!(find_200th_vaporized(&map, (11, 13)).unwrap(), (8, 2));
+    }
+
+    #[test]
+    fn test_destruction_order_non_square() {
+        use super::AsteroidMap;
+
+        let map: AsteroidMap = "#.#.#.#\n...#...\n#..#...".parse().unwrap();
+        assert_eq!(
+            map.destruction_order((3, 1)),
+            vec![(4, 0), (6, 0), (3, 2), (0, 2), (0, 0), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_render_destruction() {
+        use super::AsteroidMap;
+
+        // The station's location is marked with an asteroid so that we can
+        // parse the map.
+        let map: AsteroidMap = "
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##"
+            .parse()
+            .unwrap();
+
+        let expected = "\
+.#....###24...#..
+##...##.13#67..9#
+##...#...5.8####.
+..#.....X...###..
+..#.#.....#....##
+";
+        assert_eq!(map.render_destruction((8, 3), 9), expected);
+
+        let expected = "\
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....X...###..
+..#.#.....#....##
+";
+        assert_eq!(map.render_destruction((8, 3), 0), expected);
     }
 }
 
-pub fn run() -> ProblemResult<()> {
+fn find_200th_vaporized(map: &AsteroidMap, station: Coord) -> ProblemResult<Coord> {
+    match map.nth_vaporized(station, 200) {
+        Some(coord) => Ok(coord),
+        None => {
+            let others = map.asteroids.iter().filter(|&&a| a != station).count();
+            bail(format!(
+                "Map has only {} asteroids other than the station. Can't vaporize 200 of them.",
+                others
+            ))
+        }
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
     let map = AsteroidMap::for_problem(10)?;
 
-    // Part 1
+    // Both parts need the station location.
     let (station, max_visible) = map.best_station_coord();
-    println!("Max visibility: {} at {:?}", max_visible, station);
 
-    // Part 2
-    let coords = map.destruction_order(station);
-    println!("200th asteroid: {:?}", coords[199]);
+    if part.includes(Part::One) {
+        println!("Max visibility: {} at {:?}", max_visible, station);
+    }
+
+    if part.includes(Part::Two) {
+        let (x, y) = find_200th_vaporized(&map, station)?;
+        println!("200th asteroid: {:?}", (x, y));
+        println!("Answer: {}", x * 100 + y);
+    }
 
     Ok(())
 }
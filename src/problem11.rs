@@ -123,7 +123,7 @@ use std::convert::Into;
 
 use crate::grid::{Coord, Direction, Grid, Turn};
 use crate::intcode::{Program, IO};
-use crate::utils::{ProblemInput, ProblemResult};
+use crate::utils::{Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Color {
@@ -263,11 +263,10 @@ impl<'a> IO for RobotIO<'a> {
     }
 }
 
-pub fn run() -> ProblemResult<()> {
+pub fn run(part: Part) -> ProblemResult<()> {
     let program = Program::for_problem(11)?;
 
-    // Part 1
-    {
+    if part.includes(Part::One) {
         let mut robot = Robot::new(Color::Black);
         let mut io = RobotIO::new(&mut robot);
         program.run(&mut io)?;
@@ -277,8 +276,7 @@ pub fn run() -> ProblemResult<()> {
         );
     }
 
-    // Part 2
-    {
+    if part.includes(Part::Two) {
         let mut robot = Robot::new(Color::White);
         let mut io = RobotIO::new(&mut robot);
         program.run(&mut io)?;
@@ -286,8 +284,42 @@ pub fn run() -> ProblemResult<()> {
             "Number of painted locations: {}",
             robot.panels.initialized_count()
         );
+        // The robot's y coordinate increases to the south, which matches the
+        // top-to-bottom row order used by Grid::render, so the label comes
+        // out upright without any flipping.
         println!("Label:\n{}", robot.panels.render());
     }
 
     Ok(())
 }
+
+mod tests {
+    #[test]
+    fn test_label_height() {
+        use super::{Color, Robot, RobotIO};
+        use crate::intcode::Program;
+        use crate::utils::ProblemInput;
+
+        let program = Program::for_problem(11).unwrap();
+        let mut robot = Robot::new(Color::White);
+        let mut io = RobotIO::new(&mut robot);
+        program.run(&mut io).unwrap();
+
+        let white_rows: Vec<i64> = robot
+            .panels
+            .iter()
+            .filter(|&(_, &color)| color == Color::White)
+            .map(|(&(_, y), _)| y)
+            .collect();
+
+        let ymin = *white_rows.iter().min().unwrap();
+        let ymax = *white_rows.iter().max().unwrap();
+
+        // Registration identifiers are six pixels tall.
+        assert_eq!(ymax - ymin + 1, 6);
+
+        // The label starts at the robot's starting panel, so it should extend
+        // downward (south) from row 0.
+        assert_eq!(ymin, 0);
+    }
+}
@@ -270,9 +270,12 @@ previous state?
 
  */
 
-use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
 
-use crate::utils::ProblemResult;
+use crate::utils::cycle::find_return_to_start;
+use crate::utils::math::{abs_sum3, lcm, signum};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Clone, Copy)]
 struct Vec3 {
@@ -288,6 +291,12 @@ enum Axis {
     Z,
 }
 
+impl Axis {
+    fn all() -> [Axis; 3] {
+        [Axis::X, Axis::Y, Axis::Z]
+    }
+}
+
 impl Vec3 {
     fn new(x: i64, y: i64, z: i64) -> Vec3 {
         Vec3 { x, y, z }
@@ -298,19 +307,17 @@ impl Vec3 {
     }
 
     fn normalize(self) -> Vec3 {
-        use std::cmp::Ordering;
-        let norm = |x: i64| match x.cmp(&0) {
-            Ordering::Less => -1,
-            Ordering::Equal => 0,
-            Ordering::Greater => 1,
-        };
-
         Vec3 {
-            x: norm(self.x),
-            y: norm(self.y),
-            z: norm(self.z),
+            x: signum(self.x),
+            y: signum(self.y),
+            z: signum(self.z),
         }
     }
+
+    /// Sum of the absolute values of the coordinates.
+    fn l1(&self) -> i64 {
+        abs_sum3(self.x, self.y, self.z)
+    }
 }
 
 impl std::ops::Index<Axis> for Vec3 {
@@ -363,27 +370,62 @@ impl std::ops::SubAssign for Vec3 {
     }
 }
 
+impl FromStr for Vec3 {
+    type Err = Box<dyn Error>;
+
+    /// Parse a vector formatted like `<x=-1, y=0, z=2>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if !s.starts_with('<') || !s.ends_with('>') {
+            return bail(format!("Failed to parse vector: {}", s));
+        }
+
+        let parts: Vec<&str> = s[1..s.len() - 1].split(',').collect();
+        if parts.len() != 3 {
+            return bail(format!("Failed to parse vector: {}", s));
+        }
+
+        let names = ["x", "y", "z"];
+
+        let mut out = Vec3::zero();
+        for ((part, &name), &axis) in parts.iter().zip(names.iter()).zip(Axis::all().iter()) {
+            let kv: Vec<&str> = part.split('=').map(|p| p.trim()).collect();
+            if kv.len() != 2 || kv[0] != name {
+                return bail(format!("Failed to parse {} coordinate: {}", name, part));
+            }
+            out[axis] = kv[1].parse()?;
+        }
+
+        Ok(out)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Moons {
-    positions: [Vec3; 4],
-    velocities: [Vec3; 4],
+    positions: Vec<Vec3>,
+    velocities: Vec<Vec3>,
 }
 
 impl Moons {
-    pub fn new(positions: [Vec3; 4]) -> Moons {
+    pub fn new(positions: Vec<Vec3>) -> Moons {
+        let velocities = vec![Vec3::zero(); positions.len()];
         Moons {
             positions,
-            velocities: [Vec3::zero(); 4],
+            velocities,
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
     pub fn step(&mut self) {
         // To apply gravity, consider every pair of moons. On each axis (x, y,
         // and z), the velocity of each moon changes by exactly +1 or -1 to
         // pull the moons together.
 
-        for i in 0..4 {
-            for j in i + 1..4 {
+        for i in 0..self.len() {
+            for j in i + 1..self.len() {
                 let delta = (self.positions[i] - self.positions[j]).normalize();
 
                 self.velocities[i] -= delta;
@@ -393,192 +435,224 @@ impl Moons {
 
         // Once all gravity has been applied, apply velocity: simply add the
         // velocity of each moon to its own position.
-        for i in 0..4 {
-            self.positions[i] += self.velocities[i];
+        for (position, &velocity) in self.positions.iter_mut().zip(self.velocities.iter()) {
+            *position += velocity;
         }
     }
 
     pub fn total_energy(&self) -> i64 {
-        (0..4).map(|i| self.total_energy_for(i)).sum()
+        (0..self.len()).map(|i| self.total_energy_for(i)).sum()
     }
 
     fn total_energy_for(&self, i: usize) -> i64 {
         // A moon's potential energy is the sum of the absolute values of its
-        // x, y, and z position coordinates.
-        let pos = self.positions[i];
-        let potential = pos.x.abs() + pos.y.abs() + pos.z.abs();
-
-        let vel = self.velocities[i];
-        let kinetic = vel.x.abs() + vel.y.abs() + vel.z.abs();
-
-        potential * kinetic
+        // x, y, and z position coordinates. Its kinetic energy is the same for
+        // its velocity.
+        self.positions[i].l1() * self.velocities[i].l1()
     }
 
-    pub fn axis_state(&self, axis: Axis) -> [i64; 8] {
-        [
-            self.positions[0][axis],
-            self.positions[1][axis],
-            self.positions[2][axis],
-            self.positions[3][axis],
-            self.velocities[0][axis],
-            self.velocities[1][axis],
-            self.velocities[2][axis],
-            self.velocities[3][axis],
-        ]
+    /// The moons' positions along `axis`, followed by their velocities.
+    pub fn axis_state(&self, axis: Axis) -> Vec<i64> {
+        self.positions
+            .iter()
+            .chain(self.velocities.iter())
+            .map(|v| v[axis])
+            .collect()
     }
 }
 
-#[derive(Debug)]
-enum AxisTrace {
-    NotRepeated(HashMap<[i64; 8], u64>),
-    Repeated {
-        first: u64,
-        second: u64,
-        state: [i64; 8],
-    },
-}
+impl FromStr for Moons {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let positions = s
+            .trim()
+            .lines()
+            .map(Vec3::from_str)
+            .collect::<Result<Vec<Vec3>, Box<dyn Error>>>()?;
 
-#[derive(Debug)]
-struct Trace {
-    pub x: AxisTrace,
-    pub y: AxisTrace,
-    pub z: AxisTrace,
-    pub count: u64,
+        if positions.is_empty() {
+            return bail("Expected at least one moon");
+        }
+
+        Ok(Moons::new(positions))
+    }
 }
 
-impl Trace {
-    fn new() -> Trace {
-        Trace {
-            x: AxisTrace::NotRepeated(HashMap::new()),
-            y: AxisTrace::NotRepeated(HashMap::new()),
-            z: AxisTrace::NotRepeated(HashMap::new()),
-            count: 0,
+/// Advance a single axis of the simulation by one step.
+///
+/// `state` holds the moons' positions along the axis, followed by their
+/// velocities (the layout produced by `Moons::axis_state`).
+fn step_axis(state: &[i64]) -> Vec<i64> {
+    let mut out = state.to_vec();
+    let n = out.len() / 2;
+
+    for i in 0..n {
+        for j in i + 1..n {
+            let delta = signum(out[i] - out[j]);
+            out[n + i] -= delta;
+            out[n + j] += delta;
         }
     }
 
-    pub fn record(&mut self, moons: &Moons) -> bool {
-        let done = [Axis::X, Axis::Y, Axis::Z]
-            .iter()
-            .map(|&axis| self.update_axis(moons, axis))
-            .all(|x| x);
+    for i in 0..n {
+        out[i] += out[n + i];
+    }
 
-        self.count += 1;
+    out
+}
 
-        done
-    }
+/// Number of steps until the moons' state along `axis` first repeats.
+///
+/// The moon dynamics are reversible, so each axis is guaranteed to first
+/// repeat by returning to its initial state.
+fn axis_period(moons: &Moons, axis: Axis) -> u64 {
+    find_return_to_start(&moons.axis_state(axis), |state| step_axis(state))
+}
 
-    fn update_axis(&mut self, moons: &Moons, axis: Axis) -> bool {
-        let count = self.count;
-        let axis_trace = self.for_axis_mut(axis);
-
-        if let AxisTrace::NotRepeated(ref mut states) = axis_trace {
-            let state = moons.axis_state(axis);
-            match states.insert(state, count) {
-                // We've seen this state before. Record the count at which we
-                // first saw it, and the current count. From here on out, this
-                // axis will just cycle between these states.
-                Some(prev_count) => {
-                    *axis_trace = AxisTrace::Repeated {
-                        first: prev_count,
-                        second: count,
-                        state,
-                    };
-                }
-                None => {}
-            }
-        }
+/// Number of steps until the moons return to a previous state.
+fn period(moons: &Moons) -> u64 {
+    // Each axis evolves independently, so the full state repeats at the lcm of
+    // the per-axis periods.
+    Axis::all()
+        .iter()
+        .map(|&axis| axis_period(moons, axis))
+        .fold(1, lcm)
+}
 
-        match axis_trace {
-            AxisTrace::NotRepeated(..) => false,
-            AxisTrace::Repeated { .. } => true,
-        }
-    }
+pub fn run(part: Part) -> ProblemResult<()> {
+    let moons = Moons::for_problem(12)?;
 
-    fn cycle_length(&self, axis: Axis) -> u64 {
-        match self.for_axis(axis) {
-            AxisTrace::Repeated { first, second, .. } => *second - *first,
-            AxisTrace::NotRepeated(..) => panic!("Axis {:?} has not repeated.", axis),
+    if part.includes(Part::One) {
+        let mut moons = moons.clone();
+        for _ in 0..1000 {
+            moons.step();
         }
+        println!("Total Energy: {}", moons.total_energy());
     }
 
-    fn cycle_lengths(self) -> (u64, u64, u64) {
-        (
-            self.cycle_length(Axis::X),
-            self.cycle_length(Axis::Y),
-            self.cycle_length(Axis::Z),
-        )
+    if part.includes(Part::Two) {
+        println!("State repeats after {} steps.", period(&moons));
     }
 
-    fn for_axis_mut(&mut self, axis: Axis) -> &mut AxisTrace {
-        match axis {
-            Axis::X => &mut self.x,
-            Axis::Y => &mut self.y,
-            Axis::Z => &mut self.z,
+    Ok(())
+}
+
+mod tests {
+    #[allow(dead_code)]
+    const EXAMPLE1: &str = "<x=-1, y=0, z=2>
+<x=2, y=-10, z=-7>
+<x=4, y=-8, z=8>
+<x=3, y=5, z=-1>";
+
+    #[allow(dead_code)]
+    const EXAMPLE2: &str = "<x=-8, y=-10, z=0>
+<x=5, y=5, z=10>
+<x=2, y=-7, z=3>
+<x=9, y=-8, z=-3>";
+
+    #[allow(dead_code)]
+    fn energy_after(input: &str, steps: usize) -> i64 {
+        let mut moons: super::Moons = input.parse().unwrap();
+        for _ in 0..steps {
+            moons.step();
         }
+        moons.total_energy()
     }
 
-    fn for_axis(&self, axis: Axis) -> &AxisTrace {
-        match axis {
-            Axis::X => & self.x,
-            Axis::Y => & self.y,
-            Axis::Z => & self.z,
-        }
+    #[test]
+    fn test_energy() {
+        assert_eq!(energy_after(EXAMPLE1, 10), 179);
+        assert_eq!(energy_after(EXAMPLE2, 100), 1940);
     }
-}
 
-fn lcm(n: u64, m: u64) -> u64 {
-    (n * m) / gcd(n, m)
-}
+    #[test]
+    fn test_period() {
+        let moons: super::Moons = EXAMPLE1.parse().unwrap();
+        assert_eq!(super::period(&moons), 2772);
 
-fn gcd(mut n: u64, mut m: u64) -> u64 {
-    use std::cmp::Ordering;
+        let moons: super::Moons = EXAMPLE2.parse().unwrap();
+        assert_eq!(super::period(&moons), 4686774924);
+    }
 
-    loop {
-        match n.cmp(&m) {
-            Ordering::Equal => return n,
-            Ordering::Greater => {
-                n = n - m;
-            }
-            Ordering::Less => {
-                m = m - n;
+    #[test]
+    fn test_axis_period_matches_full_history() {
+        use super::{axis_period, step_axis, Axis, Moons};
+        use crate::utils::cycle::{find_cycle, Cycle};
+
+        // Comparing against only the initial state should agree with storing
+        // every state seen.
+        for input in [EXAMPLE1, EXAMPLE2].iter() {
+            let moons: Moons = input.parse().unwrap();
+            for &axis in Axis::all().iter() {
+                let length = axis_period(&moons, axis);
+                let cycle = find_cycle(moons.axis_state(axis), |state| step_axis(state));
+                assert_eq!(cycle, Cycle { start: 0, length });
             }
         }
     }
-}
 
-pub fn run() -> ProblemResult<()> {
-    let moons = Moons::new([
-        Vec3::new(-13, -13, -13),
-        Vec3::new(5, -8, 3),
-        Vec3::new(-6, -10, -3),
-        Vec3::new(0, 5, -5),
-    ]);
+    #[test]
+    fn test_l1() {
+        use super::Vec3;
 
-    // Part 1
-    {
-        let mut moons = moons.clone();
-        for _ in 0..1000 {
-            moons.step();
-        }
-        println!("Total Energy: {}", moons.total_energy());
+        assert_eq!(Vec3::zero().l1(), 0);
+        assert_eq!(Vec3::new(1, 2, 3).l1(), 6);
+        assert_eq!(Vec3::new(-1, 2, -3).l1(), 6);
+        assert_eq!(Vec3::new(-13, -13, -13).l1(), 39);
     }
 
-    // Part 2
-    {
-        let mut moons = moons.clone();
-        let mut trace = Trace::new();
+    #[test]
+    fn test_parse_moons() {
+        use super::Moons;
 
-        // Record until we've seen a repeat along all three axes.
-        while !trace.record(&moons) {
-            moons.step();
+        fn positions(moons: &Moons) -> Vec<(i64, i64, i64)> {
+            moons.positions.iter().map(|p| (p.x, p.y, p.z)).collect()
         }
 
-        let (xlen, ylen, zlen) = trace.cycle_lengths();
-        println!("Calculating gcd");
-        let total = lcm(lcm(xlen, ylen), zlen);
-
-        println!("State repeats after {} steps.", total);
+        let moons: Moons = EXAMPLE1.parse().unwrap();
+        assert_eq!(
+            positions(&moons),
+            vec![(-1, 0, 2), (2, -10, -7), (4, -8, 8), (3, 5, -1)]
+        );
+
+        let moons: Moons = EXAMPLE2.parse().unwrap();
+        assert_eq!(
+            positions(&moons),
+            vec![(-8, -10, 0), (5, 5, 10), (2, -7, 3), (9, -8, -3)]
+        );
+
+        // Whitespace inside the brackets is ignored.
+        let spaced =
+            "< x=-1,y = 0 , z=2 >\n<x=2, y=-10, z=-7>\n\t<x=4,y=-8,z=8>\n<x=3, y=5, z=-1>\n";
+        assert_eq!(
+            positions(&spaced.parse().unwrap()),
+            positions(&EXAMPLE1.parse().unwrap())
+        );
+
+        // Any number of moons is fine, but there has to be at least one.
+        let three: String = EXAMPLE1.lines().take(3).collect::<Vec<_>>().join("\n");
+        assert_eq!(three.parse::<Moons>().unwrap().len(), 3);
+        assert!("".parse::<Moons>().is_err());
     }
 
-    Ok(())
+    #[test]
+    fn test_three_moons() {
+        use super::Moons;
+
+        let input = "<x=-1, y=0, z=2>
+<x=2, y=-10, z=-7>
+<x=4, y=-8, z=8>";
+        let mut moons: Moons = input.parse().unwrap();
+        assert_eq!(moons.axis_state(super::Axis::X), vec![-1, 2, 4, 0, 0, 0]);
+
+        moons.step();
+        assert_eq!(moons.axis_state(super::Axis::X), vec![1, 2, 2, 2, 0, -2]);
+
+        for _ in 1..10 {
+            moons.step();
+        }
+        assert_eq!(moons.total_energy(), 190);
+        assert_eq!(super::period(&input.parse().unwrap()), 15470);
+    }
 }
@@ -58,9 +58,11 @@ block is broken?
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
 
 use crate::intcode::{Program, IO};
-use crate::utils::{ProblemInput, ProblemResult};
+use crate::utils::{Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Tile {
@@ -71,8 +73,17 @@ enum Tile {
     Ball = 4,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct BadTile(i64);
 
+impl fmt::Display for BadTile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid tile id: {}", self.0)
+    }
+}
+
+impl Error for BadTile {}
+
 impl Into<char> for Tile {
     fn into(self) -> char {
         match self {
@@ -116,6 +127,22 @@ enum Joystick {
     Right = 1,
 }
 
+/// The state of the game after the board changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GameEvent {
+    score: Option<i64>,
+    remaining_blocks: usize,
+}
+
+/// Callback invoked with a `GameEvent` each time the board changes.
+struct EventHook(Box<dyn FnMut(GameEvent)>);
+
+impl fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventHook")
+    }
+}
+
 #[derive(Debug)]
 struct Game {
     screen: HashMap<Coord, Tile>,
@@ -123,6 +150,11 @@ struct Game {
     pub score: Option<i64>,
     ball: Option<Coord>,
     paddle: Option<Coord>,
+    /// Number of block tiles currently on the screen.
+    blocks: usize,
+    /// Set if the program drew an invalid tile.
+    error: Option<BadTile>,
+    on_update: Option<EventHook>,
 }
 
 impl Game {
@@ -133,6 +165,30 @@ impl Game {
             score: None,
             ball: None,
             paddle: None,
+            blocks: 0,
+            error: None,
+            on_update: None,
+        }
+    }
+
+    /// Call `f` each time a tile is drawn or the score changes.
+    #[allow(dead_code)]
+    pub fn on_update<F: FnMut(GameEvent) + 'static>(&mut self, f: F) {
+        self.on_update = Some(EventHook(Box::new(f)));
+    }
+
+    /// Number of block tiles left on the screen.
+    pub fn remaining_blocks(&self) -> usize {
+        self.blocks
+    }
+
+    fn notify(&mut self) {
+        let event = GameEvent {
+            score: self.score,
+            remaining_blocks: self.blocks,
+        };
+        if let Some(EventHook(f)) = self.on_update.as_mut() {
+            f(event);
         }
     }
 
@@ -160,6 +216,7 @@ impl Game {
         println!("Score: {}", self.score.unwrap_or(0));
     }
 
+    #[allow(dead_code)]
     pub fn count(&self, tile: Tile) -> usize {
         self.screen.values().filter(|&&x| x == tile).count()
     }
@@ -167,7 +224,6 @@ impl Game {
 
 impl IO for Game {
     fn input(&mut self) -> Option<i64> {
-        self.paint_screen();
         match (self.ball, self.paddle) {
             (Some(ball), Some(paddle)) => {
                 match ball.0.cmp(&paddle.0) {
@@ -176,7 +232,9 @@ impl IO for Game {
                     Ordering::Greater => Some(Joystick::Right as i64),
                 }
             }
-            _ => None,
+            // The game can ask for input before the ball and paddle have both
+            // been drawn. Hold still until we know where they are.
+            _ => Some(Joystick::Neutral as i64),
         }
     }
 
@@ -191,12 +249,26 @@ impl IO for Game {
             IOState::Two(-1, 0) => {
                 self.score = Some(value);
                 self.state = IOState::Empty;
+                self.notify();
             }
             IOState::Two(first, second) => {
-                let tile = Tile::try_from(value).ok()?;
+                // Only the x=-1, y=0 path above can carry arbitrary values.
+                // Anything else must be a valid tile id.
+                let tile = match Tile::try_from(value) {
+                    Ok(tile) => tile,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return None;
+                    }
+                };
                 let coord = (usize::try_from(first).ok()?, usize::try_from(second).ok()?);
 
-                self.screen.insert(coord, tile);
+                if tile == Tile::Block {
+                    self.blocks += 1;
+                }
+                if self.screen.insert(coord, tile) == Some(Tile::Block) {
+                    self.blocks -= 1;
+                }
 
                 // Remember locations of ball and paddle for AI.
                 if tile == Tile::Ball {
@@ -207,32 +279,150 @@ impl IO for Game {
                 }
 
                 self.state = IOState::Empty;
+                self.notify();
             }
         }
         Some(())
     }
 }
 
-pub fn run() -> ProblemResult<()> {
+/// Run the game program, reporting invalid tiles as errors.
+fn play(program: &Program, game: &mut Game) -> ProblemResult<()> {
+    match program.run(game) {
+        Ok(()) => Ok(()),
+        Err(e) => match game.error {
+            Some(bad_tile) => Err(bad_tile.into()),
+            None => Err(e.into()),
+        },
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
     let program = Program::for_problem(13)?;
 
-    // Part 1
-    {
+    if part.includes(Part::One) {
         let mut game = Game::new();
-        program.run(&mut game)?;
-        println!("Number of block tiles: {}", game.count(Tile::Block));
+        play(&program, &mut game)?;
+        println!("Number of block tiles: {}", game.remaining_blocks());
     }
 
-    // Part 2
-    {
+    if part.includes(Part::Two) {
         let mut program = program; // Don't need to be mutable until here.
         program.set_address(0, 2);
 
         let mut game = Game::new();
-        program.run(&mut game)?;
+        play(&program, &mut game)?;
 
         println!("Final Score: {}", game.score.ok_or("Error running game.")?);
     }
 
     Ok(())
 }
+
+mod tests {
+    #[test]
+    fn test_bad_tile() {
+        use super::{BadTile, Game};
+        use crate::intcode::IO;
+
+        let mut game = Game::new();
+        assert_eq!(game.output(1), Some(()));
+        assert_eq!(game.output(2), Some(()));
+        assert_eq!(game.output(7), None);
+        assert_eq!(game.error, Some(BadTile(7)));
+    }
+
+    #[test]
+    fn test_update_events() {
+        use super::{Game, GameEvent, Tile};
+        use crate::intcode::IO;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let events: Rc<RefCell<Vec<GameEvent>>> = Rc::new(RefCell::new(vec![]));
+        let recorded = events.clone();
+
+        let mut game = Game::new();
+        game.on_update(move |event| recorded.borrow_mut().push(event));
+
+        #[rustfmt::skip]
+        let outputs = [
+            // Draw three blocks, a paddle, and a ball.
+            1, 1, 2,   2, 1, 2,   3, 1, 2,   2, 3, 3,   2, 2, 4,
+            // Break blocks one at a time, scoring each time.
+            1, 1, 0,   -1, 0, 10,
+            2, 1, 0,   -1, 0, 20,
+            3, 1, 0,   -1, 0, 30,
+        ];
+        for &value in outputs.iter() {
+            assert_eq!(game.output(value), Some(()));
+        }
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 11);
+        assert_eq!(
+            events[4],
+            GameEvent {
+                score: None,
+                remaining_blocks: 3
+            }
+        );
+        assert_eq!(
+            events[10],
+            GameEvent {
+                score: Some(30),
+                remaining_blocks: 0
+            }
+        );
+
+        // Once the board is drawn, blocks only ever disappear.
+        let counts: Vec<usize> = events[4..].iter().map(|e| e.remaining_blocks).collect();
+        assert!(counts.windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(game.remaining_blocks(), game.count(Tile::Block));
+    }
+
+    #[test]
+    fn test_large_score() {
+        use super::Game;
+        use crate::intcode::IO;
+
+        let mut game = Game::new();
+        for &value in [-1, 0, i64::MAX].iter() {
+            assert_eq!(game.output(value), Some(()));
+        }
+        assert_eq!(game.score, Some(i64::MAX));
+        assert_eq!(game.error, None);
+    }
+
+    #[test]
+    fn test_play_reports_bad_tile() {
+        use super::{play, Game};
+        use crate::intcode::Program;
+
+        // Output 1, 2, 9 and halt.
+        let program: Program = "104,1,104,2,104,9,99".parse().unwrap();
+        let mut game = Game::new();
+        let err = play(&program, &mut game).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid tile id: 9");
+    }
+
+    #[test]
+    fn test_input_before_ball_drawn() {
+        use super::{play, Game, Joystick};
+        use crate::intcode::Program;
+
+        // Draw the paddle at (2, 3), read an input into address 30, draw the
+        // ball at (1, 1), then output the input we received.
+        let program: Program = "104,2,104,3,104,3,3,30,104,1,104,1,104,4,4,30,99"
+            .parse()
+            .unwrap();
+        let mut game = Game::new();
+        play(&program, &mut game).unwrap();
+        assert_eq!(game.ball, Some((1, 1)));
+        assert_eq!(game.paddle, Some((2, 3)));
+
+        // The echoed input is left half-way through a draw instruction.
+        let neutral = Joystick::Neutral as i64;
+        assert!(matches!(game.state, super::IOState::One(v) if v == neutral));
+    }
+}
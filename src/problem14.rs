@@ -134,9 +134,12 @@ Although it hasn't changed, you can still get your puzzle input.
 use std::cmp::min;
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
-use crate::utils::{bail, insert_or_merge, ProblemInput, ProblemResult, SimpleError};
+use crate::graph::Graph;
+use crate::utils::math::ceil_div;
+use crate::utils::{bail, insert_or_merge, Part, ProblemInput, ProblemResult, SimpleError};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Term {
@@ -197,9 +200,48 @@ impl FromStr for Formula {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum FormulaError {
+    /// No formula produces `reagent`, which is an input to the formula for
+    /// `required_by` (or is the requested output itself, if None).
+    UnknownReagent {
+        reagent: String,
+        required_by: Option<String>,
+    },
+    /// More than one formula produces the reagent.
+    DuplicateProducer(String),
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaError::UnknownReagent {
+                reagent,
+                required_by: Some(by),
+            } => write!(
+                f,
+                "No formula producing reagent {} (required by {})",
+                reagent, by
+            ),
+            FormulaError::UnknownReagent {
+                reagent,
+                required_by: None,
+            } => write!(f, "No formula producing reagent {}", reagent),
+            FormulaError::DuplicateProducer(reagent) => {
+                write!(f, "Multiple formulas producing reagent {}", reagent)
+            }
+        }
+    }
+}
+
+impl Error for FormulaError {}
+
 #[derive(Debug)]
 struct Formulas {
     data: HashMap<String, Formula>,
+    /// Every reagent, ordered so that each comes before the inputs used to
+    /// produce it.
+    order: Vec<String>,
 }
 
 impl FromStr for Formulas {
@@ -217,31 +259,135 @@ impl FromStr for Formulas {
             })
             .collect::<Result<Vec<(String, Formula)>, Box<dyn Error>>>()?;
 
-        let data: HashMap<String, Formula> = pairs.into_iter().collect();
+        let mut data: HashMap<String, Formula> = HashMap::new();
+        for (output, formula) in pairs {
+            if data.contains_key(&output) {
+                return Err(FormulaError::DuplicateProducer(output).into());
+            }
+            data.insert(output, formula);
+        }
+
+        let mut formulas = Formulas {
+            data,
+            order: vec![],
+        };
+
+        let mut roots: Vec<String> = formulas.data.keys().cloned().collect();
+        roots.sort();
+        formulas.order = formulas
+            .topological_order(&roots)
+            .map_err(|r| SimpleError(format!("Formulas for {} form a cycle", r)))?;
 
-        Ok(Formulas { data })
+        Ok(formulas)
     }
 }
 
 impl Formulas {
+    /// Iterate over all reagents produced by some formula.
+    #[allow(dead_code)]
+    pub fn reagents(&self) -> impl Iterator<Item = &str> {
+        self.data.keys().map(|s| &s[..])
+    }
+
+    /// Get the formula that produces `reagent`, if any.
+    #[allow(dead_code)]
+    pub fn producers_of(&self, reagent: &str) -> Option<&Formula> {
+        self.data.get(reagent)
+    }
+
     fn ore_for_fuel(&self, amount: u64) -> ProblemResult<u64> {
-        produce(
-            Term {
+        // FUEL is only in `order` if a formula produces it.
+        if !self.data.contains_key("FUEL") {
+            return Err(FormulaError::UnknownReagent {
                 reagent: "FUEL".into(),
-                amount,
-            },
-            &self.data,
-        )
+                required_by: None,
+            }
+            .into());
+        }
+
+        let mut needed: HashMap<&str, u64> = HashMap::new();
+        needed.insert("FUEL", amount);
+
+        // First formula found that needs each reagent, for error reporting.
+        let mut required_by: HashMap<&str, &str> = HashMap::new();
+
+        // Every formula that uses a reagent comes before it in `order`, so
+        // by the time we reach a reagent we know the total amount needed.
+        for reagent in self.order.iter() {
+            let amount_needed = match needed.get(&reagent[..]) {
+                Some(&n) if n > 0 => n,
+                _ => continue,
+            };
+            if reagent == "ORE" {
+                continue;
+            }
+
+            let formula = self
+                .data
+                .get(reagent)
+                .ok_or_else(|| FormulaError::UnknownReagent {
+                    reagent: reagent.clone(),
+                    required_by: required_by.get(&reagent[..]).map(|&r| r.into()),
+                })?;
+
+            let iterations = ceil_div(amount_needed, formula.output.amount);
+            for input in formula.inputs.iter() {
+                required_by
+                    .entry(&input.reagent[..])
+                    .or_insert(&reagent[..]);
+                insert_or_merge(
+                    &mut needed,
+                    &input.reagent[..],
+                    input.amount * iterations,
+                    |x, y| x + y,
+                );
+            }
+        }
+
+        Ok(needed.get("ORE").cloned().unwrap_or(0))
+    }
+
+    /// Find the most FUEL that can be produced from `ore` ORE.
+    fn max_fuel_for_ore(&self, ore: u64) -> ProblemResult<u64> {
+        if self.ore_for_fuel(1)? > ore {
+            return Ok(0);
+        }
+
+        // The ore needed only grows with the amount of fuel, so double the
+        // amount until it's too much, then binary search. Throughout, `low`
+        // FUEL can be produced and `high` FUEL can't.
+        let mut low = 1;
+        let mut high = 2;
+        while self.ore_for_fuel(high)? <= ore {
+            low = high;
+            high = match high.checked_mul(2) {
+                Some(high) => high,
+                None => return bail("Maximum fuel doesn't fit in a u64"),
+            };
+        }
+
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if self.ore_for_fuel(mid)? <= ore {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low)
     }
 }
 
+#[allow(dead_code)]
 fn produce(target: Term, conversions: &HashMap<String, Formula>) -> ProblemResult<u64> {
     let mut leftovers = HashMap::new();
-    produce_inner(target, conversions, &mut leftovers)
+    produce_inner(target, None, conversions, &mut leftovers)
 }
 
 fn produce_inner(
     target: Term,
+    required_by: Option<&str>,
     conversions: &HashMap<String, Formula>,
     leftovers: &mut HashMap<String, u64>,
 ) -> ProblemResult<u64> {
@@ -261,23 +407,25 @@ fn produce_inner(
 
     let formula = conversions
         .get(&*target.reagent)
-        .ok_or(format!("No formula producingn reagent: {}", target.reagent))?;
+        .ok_or_else(|| FormulaError::UnknownReagent {
+            reagent: target.reagent.clone(),
+            required_by: required_by.map(|r| r.into()),
+        })?;
 
     // How many times do we need to run the formula to produce the needed
     // amount of target?
-    let iterations = {
-        let mut tmp = amount_to_produce / formula.output.amount;
-        if amount_to_produce % formula.output.amount > 0 {
-            tmp += 1;
-        }
-        tmp
-    };
+    let iterations = ceil_div(amount_to_produce, formula.output.amount);
 
     // Calculate how much ore we need to produce our inputs, the required
     // number of times.
     let mut total = 0;
     for input in formula.inputs.iter() {
-        total += produce_inner(input.clone() * iterations, conversions, leftovers)?;
+        total += produce_inner(
+            input.clone() * iterations,
+            Some(&target.reagent),
+            conversions,
+            leftovers,
+        )?;
     }
 
     // Record any leftover output.
@@ -289,65 +437,217 @@ fn produce_inner(
     Ok(total)
 }
 
-#[derive(Debug, Clone, Copy)]
-enum ProbeType {
-    Quadratic,
-    Linear,
+impl Graph<String> for Formulas {
+    fn edges(&self, reagent: &String) -> Vec<String> {
+        match self.data.get(reagent) {
+            Some(formula) => formula.inputs.iter().map(|t| t.reagent.clone()).collect(),
+            None => vec![],
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Probe {
-    value: u64,
-    probe_type: ProbeType,
-}
+pub fn run(part: Part) -> ProblemResult<()> {
+    let fs: Formulas = Formulas::for_problem(14)?;
 
-impl Probe {
-    fn get_value(&self) -> u64 {
-        match self.probe_type {
-            ProbeType::Quadratic => self.value * self.value,
-            ProbeType::Linear => self.value,
-        }
+    if part.includes(Part::One) {
+        println!("ORE needed to produce 1 FUEL: {}", fs.ore_for_fuel(1)?);
     }
 
-    fn increment(&mut self) {
-        self.value += 1;
+    if !part.includes(Part::Two) {
+        return Ok(());
     }
+
+    const MAX_ORE: u64 = 1_000_000_000_000;
+
+    let fuel = fs.max_fuel_for_ore(MAX_ORE)?;
+
+    println!("Max fuel produced with {} ORE: {}", MAX_ORE, fuel);
+
+    Ok(())
 }
 
-pub fn run() -> ProblemResult<()> {
-    let fs: Formulas = Formulas::for_problem(14)?;
+mod tests {
+    #[allow(dead_code)]
+    const EXAMPLE1: &str = "10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL";
 
-    // Part 1
-    println!("ORE needed to produce 1 FUEL: {}", fs.ore_for_fuel(1)?);
+    #[allow(dead_code)]
+    const EXAMPLE3: &str = "157 ORE => 5 NZVS
+165 ORE => 6 DCFZ
+44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+179 ORE => 7 PSHF
+177 ORE => 5 HKGWZ
+7 DCFZ, 7 PSHF => 2 XJWVT
+165 ORE => 2 GPVTF
+3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT";
 
-    // Part 2
-    const MAX_ORE: u64 = 1_000_000_000_000;
+    #[allow(dead_code)]
+    const EXAMPLE4: &str = "2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG
+17 NVRVD, 3 JNWZP => 8 VPVL
+53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL
+22 VJHF, 37 MNCFX => 5 FWMGM
+139 ORE => 4 NVRVD
+144 ORE => 7 JNWZP
+5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC
+5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV
+145 ORE => 6 MNCFX
+1 NVRVD => 8 CXFTF
+1 VJHF, 6 MNCFX => 4 RFSQX
+176 ORE => 6 VJHF";
 
-    let mut guess = Probe {
-        value: 1,
-        probe_type: ProbeType::Quadratic,
-    };
-    let mut prev = guess;
-
-    let fuel = loop {
-        let produced = fs.ore_for_fuel(guess.get_value())?;
-        if produced > MAX_ORE {
-            match guess.probe_type {
-                ProbeType::Quadratic => {
-                    guess = Probe {value: prev.get_value(), probe_type: ProbeType::Linear};
-                }
-                ProbeType::Linear => {
-                    break guess.get_value() - 1;
-                }
+    #[allow(dead_code)]
+    const EXAMPLE5: &str = "171 ORE => 8 CNZTR
+7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL
+114 ORE => 4 BHXH
+14 VRPVC => 6 BMBT
+6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL
+6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT
+15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW
+13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW
+5 BMBT => 4 WPTQ
+189 ORE => 9 KTJDG
+1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP
+12 VRPVC, 27 CNZTR => 2 XDBXC
+15 KTJDG, 12 BHXH => 5 XCVML
+3 BHXH, 2 VRPVC => 7 MZWV
+121 ORE => 7 VRPVC
+7 XCVML => 6 RJRHP
+5 BHXH, 4 VRPVC => 5 LTCX";
+
+    #[allow(dead_code)]
+    const EXAMPLE2: &str = "9 ORE => 2 A
+8 ORE => 3 B
+7 ORE => 5 C
+3 A, 4 B => 1 AB
+5 B, 7 C => 1 BC
+4 C, 1 A => 1 CA
+2 AB, 3 BC, 4 CA => 1 FUEL";
+
+    #[test]
+    fn test_topological_matches_recursive() {
+        use super::{produce, Formulas, Term};
+
+        for &input in [EXAMPLE1, EXAMPLE2, EXAMPLE3, EXAMPLE4, EXAMPLE5].iter() {
+            let formulas: Formulas = input.parse().unwrap();
+            for &amount in [1, 2, 7, 100].iter() {
+                let fuel = Term {
+                    reagent: "FUEL".into(),
+                    amount,
+                };
+                assert_eq!(
+                    formulas.ore_for_fuel(amount).unwrap(),
+                    produce(fuel, &formulas.data).unwrap()
+                );
             }
         }
-        else {
-            prev = guess;
-            guess.increment();
+
+        let formulas: Formulas = EXAMPLE2.parse().unwrap();
+        assert_eq!(formulas.ore_for_fuel(1).unwrap(), 165);
+    }
+
+    #[test]
+    fn test_max_fuel_for_ore() {
+        use super::Formulas;
+
+        let cases = [
+            (EXAMPLE3, 13312, 82892753),
+            (EXAMPLE4, 180697, 5586022),
+            (EXAMPLE5, 2210736, 460664),
+        ];
+        for &(input, ore_per_fuel, max_fuel) in cases.iter() {
+            let formulas: Formulas = input.parse().unwrap();
+            assert_eq!(formulas.ore_for_fuel(1).unwrap(), ore_per_fuel);
+            assert_eq!(formulas.max_fuel_for_ore(1_000_000_000_000).unwrap(), max_fuel);
         }
-    };
 
-    println!("Max fuel produced with {} ORE: {}", MAX_ORE, fuel);
+        let formulas: Formulas = EXAMPLE1.parse().unwrap();
+        assert_eq!(formulas.max_fuel_for_ore(30).unwrap(), 0);
+        assert_eq!(formulas.max_fuel_for_ore(31).unwrap(), 1);
+    }
 
-    Ok(())
+    #[test]
+    fn test_no_fuel_formula() {
+        use super::{FormulaError, Formulas};
+
+        let formulas: Formulas = "10 ORE => 10 A".parse().unwrap();
+        let expected = FormulaError::UnknownReagent {
+            reagent: "FUEL".into(),
+            required_by: None,
+        };
+
+        let err = formulas.ore_for_fuel(1).unwrap_err();
+        assert_eq!(err.downcast_ref::<FormulaError>(), Some(&expected));
+        assert_eq!(err.to_string(), "No formula producing reagent FUEL");
+
+        let err = formulas.max_fuel_for_ore(1000).unwrap_err();
+        assert_eq!(err.downcast_ref::<FormulaError>(), Some(&expected));
+    }
+
+    #[test]
+    fn test_unknown_reagent() {
+        use super::{produce, FormulaError, Formulas, Term};
+
+        // Nothing produces B.
+        let formulas: Formulas = "10 ORE => 10 A\n7 A, 1 B => 1 FUEL".parse().unwrap();
+        let expected = FormulaError::UnknownReagent {
+            reagent: "B".into(),
+            required_by: Some("FUEL".into()),
+        };
+
+        let err = formulas.ore_for_fuel(1).unwrap_err();
+        assert_eq!(err.downcast_ref::<FormulaError>(), Some(&expected));
+        assert_eq!(
+            err.to_string(),
+            "No formula producing reagent B (required by FUEL)"
+        );
+
+        let fuel = Term {
+            reagent: "FUEL".into(),
+            amount: 1,
+        };
+        let err = produce(fuel, &formulas.data).unwrap_err();
+        assert_eq!(err.downcast_ref::<FormulaError>(), Some(&expected));
+    }
+
+    #[test]
+    fn test_duplicate_producer() {
+        use super::{FormulaError, Formulas};
+
+        let err = "10 ORE => 10 A\n3 ORE => 2 A\n7 A => 1 FUEL"
+            .parse::<Formulas>()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FormulaError>(),
+            Some(&FormulaError::DuplicateProducer("A".into()))
+        );
+        assert_eq!(err.to_string(), "Multiple formulas producing reagent A");
+    }
+
+    #[test]
+    fn test_reagents_and_producers() {
+        use super::Formulas;
+        use std::str::FromStr;
+
+        let formulas = Formulas::from_str(EXAMPLE1).unwrap();
+
+        let mut reagents: Vec<&str> = formulas.reagents().collect();
+        reagents.sort();
+        assert_eq!(reagents, vec!["A", "B", "C", "D", "E", "FUEL"]);
+
+        let fuel = formulas.producers_of("FUEL").unwrap();
+        let mut inputs: Vec<(&str, u64)> = fuel
+            .inputs
+            .iter()
+            .map(|t| (&t.reagent[..], t.amount))
+            .collect();
+        inputs.sort();
+        assert_eq!(inputs, vec![("A", 7), ("E", 1)]);
+
+        assert!(formulas.producers_of("ORE").is_none());
+    }
 }
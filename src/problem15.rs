@@ -151,12 +151,10 @@ Although it hasn't changed, you can still get your puzzle input.
 
  */
 
-use std::collections::{HashMap, VecDeque};
-
-use crate::grid::{Coord, Direction, Grid};
-use crate::intcode::{Program, IO};
-use crate::tree::Tree;
-use crate::utils::{ProblemInput, ProblemResult};
+use crate::explore::MazeExplorer;
+use crate::grid::{Coord, Direction};
+use crate::intcode::{Machine, Program};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Clone, Copy)]
 enum MoveResult {
@@ -165,6 +163,17 @@ enum MoveResult {
     FoundOxygen = 2,
 }
 
+impl MoveResult {
+    /// The tile the droid tried to move onto.
+    fn tile(self) -> Tile {
+        match self {
+            MoveResult::HitWall => Tile::Wall,
+            MoveResult::Moved => Tile::Empty,
+            MoveResult::FoundOxygen => Tile::Oxygen,
+        }
+    }
+}
+
 impl From<i64> for MoveResult {
     fn from(value: i64) -> MoveResult {
         match value {
@@ -182,18 +191,6 @@ impl Into<i64> for MoveResult {
     }
 }
 
-fn direction_between(here: Coord, there: Coord) -> Direction {
-    match (there.0 - here.0, there.1 - here.1) {
-        (1, 0) => Direction::East,
-        (-1, 0) => Direction::West,
-        (0, -1) => Direction::North,
-        (0, 1) => Direction::South,
-        _ => {
-            panic!("Invalid coords: here={:?}, there={:?}", here, there);
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
     Unknown,
@@ -230,33 +227,19 @@ impl Default for Tile {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct SpanningTreeState {
-    /// Parent of coord in the shortest path back to the root.
-    parent: Option<Coord>,
-    /// Depth of coordinate in the spanning tree.
-    depth: u64,
+/// How much of the map the droid should explore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exploration {
+    /// Stop as soon as the oxygen system is found. This is enough for part 1.
+    UntilOxygen,
+    /// Explore every reachable location.
+    Full,
 }
 
 #[derive(Debug)]
 struct Droid {
-    /// Grid of currently known tile states.
-    grid: Grid<Tile>,
-
-    /// Queue of unexplored locations that are known to be reachable.
-    frontier: VecDeque<Coord>,
-
-    /// Map from explored location to parent and depth from start.
-    spanning_tree: HashMap<Coord, SpanningTreeState>,
-
-    /// Sequence of planned moves, in order from from to back.
-    plan: VecDeque<Coord>,
-
-    /// Current location.
-    location: Coord,
-
-    /// The position we're currently trying to move to.
-    moving_to: Coord,
+    /// Map of the area explored so far.
+    explorer: MazeExplorer<Tile>,
 
     /// Location of the oxygen tank.
     oxygen: Option<Coord>,
@@ -264,206 +247,179 @@ struct Droid {
 
 impl Droid {
     fn new() -> Droid {
-        let mut spanning_tree = HashMap::new();
-        spanning_tree.insert(
-            (0, 0),
-            SpanningTreeState {
-                parent: None,
-                depth: 0,
-            },
-        );
-
-        let mut droid = Droid {
-            grid: Grid::empty(),
-            frontier: VecDeque::new(),
-            spanning_tree,
-            plan: VecDeque::new(),
-            location: (0, 0),
-            moving_to: (0, 0),
+        Droid {
+            explorer: MazeExplorer::new(Tile::Start, Tile::passable),
             oxygen: None,
-        };
-
-        droid.mark_explored((0, 0), Tile::Start);
-
-        droid
-    }
-
-    fn mark_explored(&mut self, loc: Coord, tile: Tile) {
-        if self.grid.get(&loc) != Tile::Unknown {
-            return;
-        }
-
-        self.grid.set(loc, tile);
-
-        match tile {
-            Tile::Wall => {}
-            Tile::Empty | Tile::Start | Tile::Oxygen => {
-                let depth = self
-                    .spanning_tree
-                    .get(&loc)
-                    .expect(&format!("Failed to get spanning tree info for {:?}", loc))
-                    .depth;
-
-                for (coord, content) in self.grid.neighbors(&loc) {
-                    match content {
-                        Tile::Unknown => {
-                            self.spanning_tree.insert(
-                                coord,
-                                SpanningTreeState {
-                                    parent: Some(loc),
-                                    depth: depth + 1,
-                                },
-                            );
-                            self.frontier.push_back(coord);
-                        }
-                        _ => {} // Already explored here.
-                    }
-                }
-            }
-            Tile::Unknown => unreachable!("Got unknown after explore: {:?}", loc),
         }
     }
 
-    fn pop_unexplored(&mut self) -> Option<Coord> {
-        self.frontier.pop_front()
-    }
-
-    /// Compute the shortest path from self.location to goal.
-    fn path_to(&self, goal: Coord) -> Vec<Coord> {
-        self.shortest_path(self.location, goal)
-            .into_iter()
-            .collect()
+    /// Explore the area around the droid. `probe` should try to move the
+    /// droid in the given direction and report what it found.
+    fn explore<F>(&mut self, exploration: Exploration, probe: F) -> ProblemResult<()>
+    where
+        F: FnMut(Direction) -> ProblemResult<Tile>,
+    {
+        self.oxygen = match exploration {
+            Exploration::UntilOxygen => self
+                .explorer
+                .explore_until(probe, |_, tile| tile == Tile::Oxygen)?,
+            Exploration::Full => {
+                self.explorer.explore(probe)?;
+                self.explorer
+                    .grid()
+                    .iter()
+                    .find(|&(_, &tile)| tile == Tile::Oxygen)
+                    .map(|(&coord, _)| coord)
+            }
+        };
+        Ok(())
     }
 
     fn tree_depth(&self, loc: &Coord) -> Option<u64> {
-        self.spanning_tree.get(&loc).map(|state| state.depth)
-    }
-
-    fn passable_neighbors(&self, loc: Coord) -> Vec<Coord> {
-        self.grid
-            .neighbors(&loc)
-            .filter_map(
-                |(coord, tile)| {
-                    if tile.passable() {
-                        Some(coord)
-                    } else {
-                        None
-                    }
-                },
-            )
-            .collect()
+        self.explorer.depth(loc)
     }
 
     fn max_distance_from(&self, start: Coord) -> u64 {
-        let mut tree: HashMap<Coord, SpanningTreeState> = HashMap::new();
-        tree.insert(
-            start,
-            SpanningTreeState {
-                parent: None,
-                depth: 0,
-            },
-        );
+        let distances = self.explorer.grid().flood_fill(start, Tile::passable);
+        distances.values().copied().max().unwrap()
+    }
 
-        let mut queue = VecDeque::from(vec![start]);
+    /// Compute `(steps_to_oxygen, fill_minutes)` from the explored grid, or
+    /// None if the oxygen system wasn't found.
+    ///
+    /// A single full exploration is enough for both parts: the spanning tree
+    /// built while exploring gives the shortest path from the start to the
+    /// oxygen system, and the explored grid contains every open location that
+    /// oxygen can fill. The fill time is only meaningful after exploring with
+    /// `Exploration::Full`.
+    #[allow(dead_code)]
+    fn solve(&self) -> Option<(u64, u64)> {
+        let oxygen = self.oxygen?;
+        let steps = self.tree_depth(&oxygen)?;
+        Some((steps, self.max_distance_from(oxygen)))
+    }
+}
 
-        while let Some(parent) = queue.pop_front() {
-            let parent_depth = tree.get(&parent).unwrap().depth;
+fn explore(program: &Program, exploration: Exploration) -> ProblemResult<Droid> {
+    let mut machine = Machine::new(program);
+    let probe = |direction: Direction| {
+        machine.provide_input(direction.into());
+        match machine.run_until_output()? {
+            Some(status) => Ok(MoveResult::from(status).tile()),
+            None => bail("Repair droid stopped without reporting its status"),
+        }
+    };
 
-            for child in self.passable_neighbors(parent) {
-                if tree.get(&child).is_some() {
-                    continue;
-                }
+    let mut droid = Droid::new();
+    droid.explore(exploration, probe)?;
+    Ok(droid)
+}
 
-                tree.insert(
-                    child,
-                    SpanningTreeState {
-                        parent: Some(parent),
-                        depth: parent_depth + 1,
-                    },
-                );
-                queue.push_back(child);
-            }
-        }
+pub fn run(part: Part) -> ProblemResult<()> {
+    let program = Program::for_problem(15)?;
 
-        tree.values().map(|v| v.depth).max().unwrap()
+    // Part 2 needs the whole map, but part 1 only needs the way to oxygen.
+    let exploration = if part == Part::One {
+        Exploration::UntilOxygen
+    } else {
+        Exploration::Full
+    };
+    let droid = explore(&program, exploration)?;
+
+    println!("{}", droid.explorer.grid().render());
+
+    let loc = match droid.oxygen {
+        Some(loc) => loc,
+        None => return bail("Failed to find oxygen tank!"),
+    };
+
+    if part.includes(Part::One) {
+        let steps = match droid.tree_depth(&loc) {
+            Some(steps) => steps,
+            None => return bail("Oxygen tank isn't reachable from the entrance"),
+        };
+        println!("Found oxygen at {:?}", loc);
+        println!("Steps from entrance: {:?}", steps);
     }
-}
 
-impl Tree<Coord> for Droid {
-    fn parent(&self, node: Coord) -> Option<Coord> {
-        match self.spanning_tree.get(&node) {
-            Some(state) => state.parent,
-            None => None,
-        }
+    if part.includes(Part::Two) {
+        println!(
+            "Max distance from oxygen: {:?}",
+            droid.max_distance_from(loc)
+        );
     }
+
+    Ok(())
 }
 
-impl IO for Droid {
-    fn input(&mut self) -> Option<i64> {
-        // Choose a new location to explore to, if necessary.
-        if self.plan.len() == 0 {
-            let new_goal = match self.pop_unexplored() {
-                Some(loc) => loc,
-                None => return None, // Nothing more to explore.
-            };
+mod tests {
+    #[test]
+    fn test_stop_at_oxygen() {
+        use super::{explore, Exploration};
+        use crate::intcode::Program;
+        use crate::utils::ProblemInput;
 
-            self.plan = self.path_to(new_goal).into_iter().collect();
-            self.plan.pop_front(); // Path contains current location. Remove it.
+        let program = Program::for_problem(15).unwrap();
 
-            assert!(self.plan.len() > 0);
-        }
+        let full = explore(&program, Exploration::Full).unwrap();
+        let partial = explore(&program, Exploration::UntilOxygen).unwrap();
+
+        let oxygen = full.oxygen.unwrap();
+        assert_eq!(partial.oxygen, Some(oxygen));
+        assert_eq!(partial.tree_depth(&oxygen), full.tree_depth(&oxygen));
 
-        // Move toward the next location in the plan.
-        let next_location = self.plan.pop_front().unwrap();
-        self.moving_to = next_location;
-        return Some(direction_between(self.location, next_location) as i64);
+        // Stopping early should explore strictly less of the map.
+        let explored = |droid: &super::Droid| droid.explorer.grid().initialized_count();
+        assert!(explored(&partial) < explored(&full));
     }
 
-    fn output(&mut self, value: i64) -> Option<()> {
-        match MoveResult::from(value) {
-            MoveResult::HitWall => {
-                self.mark_explored(self.moving_to, Tile::Wall);
-            }
-            MoveResult::Moved => {
-                self.mark_explored(self.moving_to, Tile::Empty);
-                self.location = self.moving_to;
-            }
-            MoveResult::FoundOxygen => {
-                self.mark_explored(self.moving_to, Tile::Oxygen);
-                self.location = self.moving_to;
-                self.oxygen = Some(self.location);
+    #[test]
+    fn test_solve_hand_built_maze() {
+        use super::{Droid, Exploration, Tile};
+        use crate::grid::Direction;
+        use crate::utils::ProblemResult;
+        use std::collections::HashMap;
+
+        // D marks the droid's starting location.
+        let maze = "
+#######
+#D....#
+#.###.#
+#.#O..#
+#######";
+
+        let mut tiles = HashMap::new();
+        let mut start = (0, 0);
+        for (y, line) in maze.trim().lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let coord = (x as i64, y as i64);
+                if c == 'D' {
+                    start = coord;
+                }
+                tiles.insert(coord, c);
             }
         }
 
-        Some(())
-    }
-}
-
-pub fn run() -> ProblemResult<()> {
-    let program = Program::for_problem(15)?;
-
-    let mut droid = Droid::new();
-
-    // We expect to end with error because we terminate by returning an error
-    // status from an input() call.
-    program.run(&mut droid).unwrap_err();
-
-    println!("{}", droid.grid.render());
+        // Drive the droid by hand, playing the part of the repair program.
+        let mut droid = Droid::new();
+        let mut location = start;
+        let probe = |direction: Direction| -> ProblemResult<Tile> {
+            let target = location + direction;
+            let tile = match tiles[&target] {
+                '#' => Tile::Wall,
+                'O' => Tile::Oxygen,
+                _ => Tile::Empty,
+            };
+            if tile != Tile::Wall {
+                location = target;
+            }
+            Ok(tile)
+        };
+        droid.explore(Exploration::Full, probe).unwrap();
 
-    match droid.oxygen {
-        Some(ref loc) => {
-            // Part 1.
-            println!("Found oxygen at {:?}", loc);
-            println!("Steps from entrance: {:?}", droid.tree_depth(loc).unwrap());
+        assert_eq!(droid.oxygen, Some((2, 2)));
+        assert_eq!(droid.solve(), Some((8, 10)));
 
-            // Part 2
-            println!(
-                "Max distance from oxygen: {:?}",
-                droid.max_distance_from(*loc)
-            );
-        }
-        None => panic!("Failed to find oxygen tank!"),
+        assert_eq!(Droid::new().solve(), None);
     }
-
-    Ok(())
 }
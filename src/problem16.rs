@@ -131,15 +131,13 @@ what is the eight-digit message embedded in the final output list?
 
 */
 
-use std::cell::RefCell;
-use std::cmp::max;
-use std::collections::HashMap;
+use std::cmp::{max, min};
 use std::fmt;
 use std::iter::{repeat, successors};
 use std::rc::Rc;
 use std::str::FromStr;
 
-use crate::utils::{ProblemInput, ProblemResult};
+use crate::utils::{bail, Memo, Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 struct MemoKey {
@@ -150,47 +148,47 @@ struct MemoKey {
 #[derive(Debug)]
 struct Digits {
     len: usize,
-    // Memoized map from (iteration, index) -> result.  This is stored in a
-    // refcell to allow updating the memo table from non-mut methods.
-    memo: RefCell<HashMap<MemoKey, u8>>,
+    // The initial signal.
+    digits: Vec<u8>,
+    // Memoized map from (iteration, index) -> result, for iterations after 0.
+    memo: Memo<MemoKey, u8>,
     // Map from output index to (input_index, coefficient) pairs.
     //
-    // Putting this in a refcell allows us to mutate it from non-mut methods, and
-    // returning refcounted pointers to the coefficients allows multiple
+    // Returning refcounted pointers to the coefficients allows multiple
     // recursive calls to borrow the same coefficient vector safely.
-    coefs: RefCell<HashMap<usize, Rc<Vec<(usize, i8)>>>>,
+    coefs: Memo<usize, Rc<Vec<(usize, i8)>>>,
 }
 
 impl Digits {
     fn from_vec(v: Vec<u8>) -> Digits {
-        let len = v.len();
-        let memo = v
-            .into_iter()
-            .enumerate()
-            .map(|(ix, val)| (MemoKey { iteration: 0, ix }, val))
-            .collect();
-
         Digits {
-            len,
-            memo: RefCell::new(memo),
-            coefs: RefCell::new(HashMap::new()),
+            len: v.len(),
+            digits: v,
+            memo: Memo::new(),
+            coefs: Memo::new(),
         }
     }
 
     fn replicated(&self, n: usize) -> Digits {
-        let len = self.len;
-        let mut data = Vec::with_capacity(len * n);
-        let memo = self.memo.borrow();
-
-        for ix in 0..len {
-            data.push(*memo.get(&MemoKey { ix, iteration: 0 }).unwrap());
-        }
-        data = data.into_iter().cycle().take(len * n).collect();
-
+        let data = self
+            .digits
+            .iter()
+            .copied()
+            .cycle()
+            .take(self.len * n)
+            .collect();
         Digits::from_vec(data)
     }
 
     fn message(&self, iterations: u64, offset: usize, len: usize) -> u64 {
+        from_digits(&self.after_phases(iterations)[offset..offset + len])
+    }
+
+    /// Like `message`, but computes only the digits needed for the message,
+    /// memoizing intermediate digits from every phase.
+    #[allow(dead_code)]
+    fn memoized_message(&self, iterations: u64, offset: usize, len: usize) -> u64 {
+        self.reserve_memo(iterations);
         self.precompute_tail(iterations, offset);
 
         let digits: Vec<u8> = (offset..offset + len)
@@ -200,31 +198,87 @@ impl Digits {
         from_digits(&digits[..])
     }
 
-    fn message_offset(&mut self) -> usize {
-        self.message(0, 0, 7) as usize
+    /// Like `message`, but much faster when `offset` is in the second half of
+    /// the signal.
+    ///
+    /// In the second half of the signal, every coefficient at or after the
+    /// output position is 1, and every coefficient before it is 0. Each phase
+    /// therefore replaces every digit with the sum of itself and the digits
+    /// after it, mod 10, which we can compute with a running sum from the end
+    /// of the signal.
+    ///
+    /// Returns None if the message would run past the end of the signal.
+    fn fast_message(&self, iterations: u64, offset: usize, len: usize) -> Option<u64> {
+        if offset.checked_add(len)? > self.len {
+            return None;
+        }
+        if offset < self.len / 2 {
+            return Some(self.message(iterations, offset, len));
+        }
+
+        let mut tail = self.digits[offset..].to_vec();
+        for _ in 0..iterations {
+            let mut sum = 0;
+            for digit in tail.iter_mut().rev() {
+                sum = (sum + *digit) % 10;
+                *digit = sum;
+            }
+        }
+
+        Some(from_digits(&tail[..len]))
+    }
+
+    /// Compute the full signal after `n` phases of FFT.
+    ///
+    /// Only the current phase and the one being computed are kept, so this
+    /// uses memory proportional to the length of the signal.
+    fn after_phases(&self, n: u64) -> Vec<u8> {
+        let mut current = self.digits.clone();
+        let mut next = vec![0; self.len];
+        let mut prefix_sums = Vec::with_capacity(self.len + 1);
+
+        for _ in 0..n {
+            apply_phase(&current, &mut next, &mut prefix_sums);
+            std::mem::swap(&mut current, &mut next);
+        }
+
+        current
+    }
+
+    /// The offset given by the first 7 digits, or None if the signal is too
+    /// short.
+    fn message_offset(&mut self) -> Option<usize> {
+        self.digits.get(..7).map(|d| from_digits(d) as usize)
+    }
+
+    /// Forget all memoized digits and coefficients.
+    #[allow(dead_code)]
+    fn clear_memo(&self) {
+        self.memo.clear();
+        self.coefs.clear();
+    }
+
+    /// Make room in the memo tables for `iterations` phases of the full
+    /// signal.
+    fn reserve_memo(&self, iterations: u64) {
+        self.memo.reserve(self.len * iterations as usize);
+        self.coefs.reserve(self.len);
     }
 
     fn precompute_tail(&self, iterations: u64, offset: usize) {
         let precompute_start = max(offset, self.len / 2 + 1);
         let precompute_ixs = precompute_start..self.len;
-        let mut memo = self.memo.borrow_mut();
 
         for iteration in 1..=iterations {
             let prev: Vec<(usize, u8)> = precompute_ixs
                 .clone()
-                .map(|ix| {
-                    let ref key = MemoKey {
-                        iteration: iteration - 1,
-                        ix,
-                    };
-                    (ix, *memo.get(&key).unwrap())
-                })
+                .map(|ix| (ix, self.get(iteration - 1, ix)))
                 .collect();
 
             let mut cumsum: i64 = 0;
             for &(ix, val) in prev.iter().rev() {
                 cumsum += val as i64;
-                memo.insert(
+                self.memo.insert(
                     MemoKey {
                         iteration: iteration,
                         ix: ix,
@@ -240,33 +294,24 @@ impl Digits {
             panic!("ix ({}) > self.len ({})", ix, self.len);
         }
 
-        let ref key = MemoKey { iteration, ix };
-        if let Some(&value) = self.memo.borrow().get(key) {
-            return value;
+        if iteration == 0 {
+            return self.digits[ix];
         }
 
-        let result: u8 = {
+        let key = MemoKey { iteration, ix };
+        self.memo.get_or_compute(key, || {
             let mut tmp = 0;
             let coefs = self.get_coefs(ix);
             for &(sub_ix, coef) in coefs.iter() {
                 tmp += (coef as i64) * (self.get(iteration - 1, sub_ix) as i64);
             }
             (tmp.abs() % 10) as u8
-        };
-
-        self.memo.borrow_mut().insert(*key, result);
-        result
+        })
     }
 
     fn get_coefs(&self, ix: usize) -> Rc<Vec<(usize, i8)>> {
-        if let Some(rc) = self.coefs.borrow().get(&ix) {
-            return Rc::clone(rc);
-        }
-
-        let result = Rc::new(self.compute_coefs(ix));
-        self.coefs.borrow_mut().insert(ix, Rc::clone(&result));
-
-        result
+        self.coefs
+            .get_or_compute(ix, || Rc::new(self.compute_coefs(ix)))
     }
 
     fn compute_coefs(&self, ix: usize) -> Vec<(usize, i8)> {
@@ -283,6 +328,38 @@ impl Digits {
     }
 }
 
+/// Apply a single phase of FFT to `input`, writing the result to `output`.
+///
+/// `prefix_sums` is scratch space for running sums of the input, which let us
+/// add up each block of equal coefficients in constant time.
+fn apply_phase(input: &[u8], output: &mut [u8], prefix_sums: &mut Vec<i64>) {
+    let len = input.len();
+
+    prefix_sums.clear();
+    prefix_sums.push(0);
+    let mut total = 0;
+    for &digit in input {
+        total += digit as i64;
+        prefix_sums.push(total);
+    }
+    let block_sum =
+        |start: usize, end: usize| prefix_sums[min(end, len)] - prefix_sums[min(start, len)];
+
+    for (ix, digit) in output.iter_mut().enumerate() {
+        // The pattern for output `ix` is blocks of `ix + 1` zeros, ones, zeros
+        // and negative ones, shifted left by one place.
+        let block = ix + 1;
+        let mut sum = 0;
+        let mut start = ix;
+        while start < len {
+            sum += block_sum(start, start + block);
+            sum -= block_sum(start + 2 * block, start + 3 * block);
+            start += 4 * block;
+        }
+        *digit = (sum.abs() % 10) as u8;
+    }
+}
+
 fn from_digits(digits: &[u8]) -> u64 {
     let powers_of_10 = successors(Some(1_u64), |n| Some(n * 10));
     digits
@@ -323,19 +400,129 @@ impl fmt::Display for BadDigit {
 
 impl std::error::Error for BadDigit {}
 
-pub fn run() -> ProblemResult<()> {
+pub fn run(part: Part) -> ProblemResult<()> {
     let digits = Digits::for_problem(16)?;
 
-    // Part 1
-    let result = digits.message(100, 0, 8);
-    println!("First 8 digits: {}", result);
-
-    // Part 2 (too slow to run)
+    if part.includes(Part::One) {
+        let result = digits.message(100, 0, 8);
+        println!("First 8 digits: {}", result);
+    }
 
-    let mut big_digits = digits.replicated(10000);
-    let offset = big_digits.message_offset();
-    println!("message offset: {}", offset);
-    println!("Secret Message: {}", big_digits.message(100, offset, 8));
+    if part.includes(Part::Two) {
+        let mut big_digits = digits.replicated(10000);
+        let offset = match big_digits.message_offset() {
+            Some(offset) => offset,
+            None => return bail("Signal is too short to contain a message offset"),
+        };
+        println!("message offset: {}", offset);
+        match big_digits.fast_message(100, offset, 8) {
+            Some(message) => println!("Secret Message: {}", message),
+            None => {
+                return bail(format!(
+                    "Message offset {} is past the end of the signal",
+                    offset
+                ))
+            }
+        }
+    }
 
     Ok(())
 }
+
+mod tests {
+    #[test]
+    fn test_after_phases() {
+        use super::Digits;
+
+        let digits: Digits = "12345678".parse().unwrap();
+        assert_eq!(digits.after_phases(0), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(digits.after_phases(1), vec![4, 8, 2, 2, 6, 1, 5, 8]);
+        assert_eq!(digits.after_phases(2), vec![3, 4, 0, 4, 0, 4, 3, 8]);
+        assert_eq!(digits.after_phases(3), vec![0, 3, 4, 1, 5, 5, 1, 8]);
+        assert_eq!(digits.after_phases(4), vec![0, 1, 0, 2, 9, 4, 9, 8]);
+    }
+
+    #[test]
+    fn test_message() {
+        use super::Digits;
+
+        let cases = [
+            ("80871224585914546619083218645595", 24176176),
+            ("19617804207202209144916044189917", 73745418),
+            ("69317163492948606335995924319873", 52432133),
+        ];
+
+        for &(input, expected) in cases.iter() {
+            let digits: Digits = input.parse().unwrap();
+            assert_eq!(digits.message(100, 0, 8), expected);
+        }
+    }
+
+    #[test]
+    fn test_lean_matches_memoized() {
+        use super::Digits;
+
+        let inputs = [
+            "12345678",
+            "80871224585914546619083218645595",
+            "19617804207202209144916044189917",
+            "69317163492948606335995924319873",
+        ];
+
+        for &input in inputs.iter() {
+            let digits: Digits = input.parse().unwrap();
+            for &phases in [0, 1, 4, 100].iter() {
+                let memoized: Vec<u8> = (0..digits.len).map(|ix| digits.get(phases, ix)).collect();
+                assert_eq!(digits.after_phases(phases), memoized);
+            }
+
+            let expected = digits.memoized_message(100, 0, 8);
+            assert_eq!(digits.message(100, 0, 8), expected);
+
+            digits.clear_memo();
+            assert_eq!(digits.memo.len(), 0);
+            assert_eq!(digits.coefs.len(), 0);
+            assert_eq!(digits.memoized_message(100, 0, 8), expected);
+        }
+    }
+
+    #[test]
+    fn test_fast_message() {
+        use super::Digits;
+
+        let cases = [
+            ("03036732577212944063491565474664", 84462026),
+            ("02935109699940807407585447034323", 78725270),
+            ("03081770884921959731165446850517", 53553731),
+        ];
+
+        for &(input, expected) in cases.iter() {
+            let digits: Digits = input.parse().unwrap();
+            let mut big_digits = digits.replicated(10000);
+            let offset = big_digits.message_offset().unwrap();
+            assert_eq!(big_digits.fast_message(100, offset, 8), Some(expected));
+        }
+
+        // Offsets in the first half of the signal fall back to `message`.
+        let digits: Digits = "80871224585914546619083218645595".parse().unwrap();
+        assert_eq!(digits.fast_message(100, 0, 8), Some(24176176));
+
+        // Both methods agree in the second half.
+        let digits: Digits = "69317163492948606335995924319873".parse().unwrap();
+        assert_eq!(
+            digits.fast_message(100, 20, 8),
+            Some(digits.message(100, 20, 8))
+        );
+
+        // The message has to fit in the signal.
+        assert_eq!(
+            digits.fast_message(100, 24, 8),
+            Some(digits.message(100, 24, 8))
+        );
+        assert_eq!(digits.fast_message(100, 25, 8), None);
+        assert_eq!(digits.fast_message(100, usize::MAX, 8), None);
+
+        let mut digits: Digits = "123456".parse().unwrap();
+        assert_eq!(digits.message_offset(), None);
+    }
+}
@@ -190,7 +190,7 @@ vacuum robot report it has collected?
 
 use crate::grid::{Coord, Grid};
 use crate::intcode::{Program, StaticIO};
-use crate::utils::{ProblemInput, ProblemResult};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
 
 mod cell {
     use std::collections::HashMap;
@@ -277,9 +277,12 @@ mod cell {
             self.get(&c) == Cell::Empty
         }
 
-        pub fn as_move_sequence(&self) -> Vec<Move> {
+        /// Moves that walk the robot from its starting point to the end of the
+        /// scaffold, going straight through intersections, or None if there's
+        /// no robot on the grid.
+        pub fn as_move_sequence(&self) -> Option<Vec<Move>> {
             let mut out: Vec<Move> = Vec::new();
-            let (mut loc, mut direction) = self.find_robot();
+            let (mut loc, mut direction) = self.find_robot()?;
 
             loop {
                 if self.is_empty(loc + direction) {
@@ -293,31 +296,37 @@ mod cell {
                     });
 
                     match next_turn {
-                        Some(t) => {
-                            println!("loc={:?}, direction={:?}, turn={:?}", loc, direction, t);
-                            direction = t.apply(direction);
-                            out.push(Move::Rotate(t));
-                            out.push(Move::Forward(1));
+                        Some(turn) => {
+                            direction = turn.apply(direction);
+                            out.push(Move {
+                                turn: Some(turn),
+                                distance: 1,
+                            });
                         }
                         None => {
-                            return out;
+                            return Some(out);
                         }
                     }
                 } else {
-                    out.last_mut().unwrap().increment();
+                    match out.last_mut() {
+                        Some(last) => last.distance += 1,
+                        // The robot already faces along the scaffold.
+                        None => out.push(Move {
+                            turn: None,
+                            distance: 1,
+                        }),
+                    }
                 }
 
                 loc = loc + direction;
             }
         }
 
-        fn find_robot(&self) -> (Coord, Direction) {
-            self.iter()
-                .find_map(|(coord, tile)| match tile {
-                    Cell::Robot(d) => Some((*coord, *d)),
-                    _ => None,
-                })
-                .expect("No robot on grid!")
+        fn find_robot(&self) -> Option<(Coord, Direction)> {
+            self.iter().find_map(|(coord, tile)| match tile {
+                Cell::Robot(d) => Some((*coord, *d)),
+                _ => None,
+            })
         }
     }
 
@@ -332,27 +341,144 @@ mod cell {
 
     impl std::error::Error for BadCell {}
 
-    #[derive(Debug)]
-    pub enum Move {
-        Forward(u64),
-        Rotate(Turn),
+    /// A turn followed by some number of steps forward. Only the first move
+    /// can go forward without turning.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Move {
+        pub turn: Option<Turn>,
+        pub distance: u64,
     }
 
-    impl Move {
-        pub fn increment(&mut self) {
-            match self {
-                Move::Forward(i) => {
-                    *self = Move::Forward(*i + 1);
+    impl fmt::Display for Move {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let turn = match self.turn {
+                Some(Turn::CW) => "R,",
+                Some(Turn::CCW) => "L,",
+                Some(Turn::Around) => "R,R,",
+                None => "",
+            };
+            write!(f, "{}{}", turn, self.distance)
+        }
+    }
+}
+
+use cell::{Cell, Move};
+
+/// Maximum number of characters in the main routine or a movement function,
+/// not counting the newline.
+const MAX_ROUTINE_LEN: usize = 20;
+const NUM_FUNCTIONS: usize = 3;
+
+/// A main routine calling up to three movement functions (A, B, and C).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Routine {
+    main: Vec<usize>,
+    functions: Vec<Vec<Move>>,
+}
+
+impl Routine {
+    /// Split `moves` into a main routine and movement functions that fit in
+    /// the robot's memory.
+    fn compress(moves: &[Move]) -> Option<Routine> {
+        let mut routine = Routine {
+            main: vec![],
+            functions: vec![],
+        };
+        if routine.search(moves) {
+            Some(routine)
+        } else {
+            None
+        }
+    }
+
+    /// Depth-first search for a way to cover `remaining` with calls to
+    /// existing functions, defining new ones as needed.
+    fn search(&mut self, remaining: &[Move]) -> bool {
+        if remaining.is_empty() {
+            return true;
+        }
+
+        // Each call takes two characters ("A,"), except the last one.
+        if self.main.len() * 2 + 1 > MAX_ROUTINE_LEN {
+            return false;
+        }
+
+        for i in 0..self.functions.len() {
+            let len = self.functions[i].len();
+            if remaining.starts_with(&self.functions[i]) {
+                self.main.push(i);
+                if self.search(&remaining[len..]) {
+                    return true;
                 }
-                _ => panic!("Can't extend non-forward move: {:?}", self),
+                self.main.pop();
             }
         }
+
+        if self.functions.len() < NUM_FUNCTIONS {
+            for len in 1..=remaining.len() {
+                let candidate = &remaining[..len];
+                if render_moves(candidate).len() > MAX_ROUTINE_LEN {
+                    break;
+                }
+
+                self.main.push(self.functions.len());
+                self.functions.push(candidate.to_vec());
+                if self.search(&remaining[len..]) {
+                    return true;
+                }
+                self.functions.pop();
+                self.main.pop();
+            }
+        }
+
+        false
+    }
+
+    /// The moves made by running the main routine.
+    #[allow(dead_code)]
+    fn expand(&self) -> Vec<Move> {
+        self.main
+            .iter()
+            .flat_map(|&i| self.functions[i].iter().copied())
+            .collect()
+    }
+
+    /// Lines to send to the robot, ending with the answer to "Continuous
+    /// video feed?".
+    fn input_lines(&self) -> Vec<String> {
+        let main: Vec<String> = self
+            .main
+            .iter()
+            .map(|&i| ((b'A' + i as u8) as char).to_string())
+            .collect();
+
+        let mut lines = vec![main.join(",")];
+        for i in 0..NUM_FUNCTIONS {
+            match self.functions.get(i) {
+                Some(f) => lines.push(render_moves(f)),
+                // The robot always asks for all three functions.
+                None => lines.push("".into()),
+            }
+        }
+        lines.push("n".into());
+        lines
     }
 }
 
-use cell::Cell;
+fn render_moves(moves: &[Move]) -> String {
+    let parts: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+    parts.join(",")
+}
+
+fn ascii_input(lines: &[String]) -> Vec<i64> {
+    lines
+        .iter()
+        .flat_map(|line| line.bytes().chain(std::iter::once(b'\n')))
+        .map(|b| b as i64)
+        .collect()
+}
 
-pub fn run() -> ProblemResult<()> {
+pub fn run(part: Part) -> ProblemResult<()> {
     let program = Program::for_problem(17)?;
 
     let mut io = StaticIO::new(vec![]);
@@ -362,32 +488,46 @@ pub fn run() -> ProblemResult<()> {
     let grid = Grid::<Cell>::from_program_output(results)?;
     println!("{}", grid.render());
 
-    // Part 1
-    {
+    if part.includes(Part::One) {
         let intersections = get_intersections(&grid);
         let total: i64 = intersections.map(|(x, y)| x * y).sum();
         println!("Total: {}", total);
     }
 
-    // Part 2
-    {
-        let sequence = grid.as_move_sequence();
-        println!("{:?}", sequence);
+    if part.includes(Part::Two) {
+        let sequence = match grid.as_move_sequence() {
+            Some(sequence) => sequence,
+            None => return bail("No robot on grid"),
+        };
+        let routine = match Routine::compress(&sequence) {
+            Some(r) => r,
+            None => return bail("Couldn't split path into movement functions"),
+        };
+
+        let lines = routine.input_lines();
+        for line in lines.iter() {
+            println!("{}", line);
+        }
+
+        let mut program = program;
+        program.set_address(0, 2);
+
+        let mut io = StaticIO::new(ascii_input(&lines));
+        program.run(&mut io)?;
+
+        match io.outputs().last() {
+            Some(&dust) if dust > 127 => println!("Dust collected: {}", dust),
+            _ => return bail("Robot didn't report any dust"),
+        }
     }
 
     Ok(())
 }
 
-fn get_intersections<'a>(grid: &'a Grid<Cell>) -> impl Iterator<Item = &'a Coord> {
-    grid.iter()
+fn get_intersections(grid: &Grid<Cell>) -> impl Iterator<Item = Coord> + '_ {
+    grid.coords()
         // Filter to nonempty cells.
-        .filter_map(|(coord, cell)| {
-            if *cell != Cell::Empty {
-                Some(coord)
-            } else {
-                None
-            }
-        })
+        .filter(move |coord| grid.get(coord) != Cell::Empty)
         // Intersections are locations with at least 3 nonempty neighbors.
         .filter(move |coord| {
             let num_adjacent = grid
@@ -397,3 +537,88 @@ fn get_intersections<'a>(grid: &'a Grid<Cell>) -> impl Iterator<Item = &'a Coord
             num_adjacent >= 3
         })
 }
+
+mod tests {
+    #[allow(dead_code)]
+    fn grid_from_str(s: &str) -> super::Grid<super::Cell> {
+        let output: Vec<i64> = s.trim().bytes().map(|b| b as i64).collect();
+        super::Grid::from_program_output(output).unwrap()
+    }
+
+    #[test]
+    fn test_alignment_sum() {
+        use super::get_intersections;
+
+        let grid = grid_from_str(
+            "
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+",
+        );
+
+        let mut intersections: Vec<_> = get_intersections(&grid).collect();
+        intersections.sort();
+        assert_eq!(intersections, vec![(2, 2), (2, 4), (6, 4), (10, 4)]);
+
+        let total: i64 = intersections.iter().map(|(x, y)| x * y).sum();
+        assert_eq!(total, 76);
+    }
+
+    #[test]
+    fn test_compress_example_path() {
+        use super::{render_moves, Routine};
+
+        let grid = grid_from_str(
+            "
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......
+",
+        );
+
+        let moves = grid.as_move_sequence().unwrap();
+        assert_eq!(
+            render_moves(&moves),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+
+        let routine = Routine::compress(&moves).unwrap();
+        assert_eq!(routine.expand(), moves);
+        assert!(routine.functions.len() <= 3);
+        for line in routine.input_lines() {
+            assert!(line.len() <= 20);
+        }
+    }
+
+    #[test]
+    fn test_move_sequence_without_first_turn() {
+        use super::render_moves;
+
+        // The robot starts out facing along the scaffold.
+        let grid = grid_from_str("#\n#\n^");
+        assert_eq!(render_moves(&grid.as_move_sequence().unwrap()), "2");
+
+        let grid = grid_from_str("..#\n..#\n^##");
+        assert_eq!(render_moves(&grid.as_move_sequence().unwrap()), "R,2,L,2");
+
+        let grid = grid_from_str("##\n##");
+        assert_eq!(grid.as_move_sequence(), None);
+    }
+}
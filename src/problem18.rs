@@ -0,0 +1,317 @@
+/*! --- Day 18: Many-Worlds Interpretation ---
+
+The tunnels beneath Triton are a maze of open passages (.), stone walls (#),
+keys (lowercase letters), and doors (uppercase letters). You start at the
+entrance (@). Picking up a key opens the door with the matching letter.
+
+Part 1: How many steps is the shortest path that collects all of the keys?
+
+Part 2: The area around the entrance is actually:
+
+@#@
+###
+@#@
+
+so there are four vaults, each with its own robot. Robots move one at a time,
+and a key collected by any robot opens its door for all of them. What is the
+fewest steps necessary to collect all of the keys?
+*/
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+use crate::grid::{Coord, Grid};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult, SimpleError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Tile {
+    #[default]
+    Wall,
+    Open,
+    Entrance,
+    Key(u8),
+    Door(u8),
+}
+
+impl Tile {
+    fn from_char(c: char) -> Option<Tile> {
+        match c {
+            '#' => Some(Tile::Wall),
+            '.' => Some(Tile::Open),
+            '@' => Some(Tile::Entrance),
+            'a'..='z' => Some(Tile::Key(c as u8 - b'a')),
+            'A'..='Z' => Some(Tile::Door(c as u8 - b'A')),
+            _ => None,
+        }
+    }
+}
+
+impl From<Tile> for char {
+    fn from(tile: Tile) -> char {
+        match tile {
+            Tile::Wall => '#',
+            Tile::Open => '.',
+            Tile::Entrance => '@',
+            Tile::Key(k) => (b'a' + k) as char,
+            Tile::Door(d) => (b'A' + d) as char,
+        }
+    }
+}
+
+/// Set of keys, stored as a bitmask with bit `k` set for key `k`.
+type Keys = u32;
+
+/// A shortest walk from some location to a key.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    key: u8,
+    coord: Coord,
+    distance: u64,
+    /// Doors passed through along the way.
+    doors: Keys,
+}
+
+#[derive(Debug, Clone)]
+struct Vault {
+    grid: Grid<Tile>,
+}
+
+impl Vault {
+    fn entrances(&self) -> Vec<Coord> {
+        let mut out: Vec<Coord> = self
+            .grid
+            .iter()
+            .filter(|&(_, &tile)| tile == Tile::Entrance)
+            .map(|(&coord, _)| coord)
+            .collect();
+        out.sort();
+        out
+    }
+
+    fn all_keys(&self) -> Keys {
+        self.grid.values().fold(0, |keys, tile| match tile {
+            Tile::Key(k) => keys | (1 << k),
+            _ => keys,
+        })
+    }
+
+    /// Shortest walks from `start` to every reachable key.
+    fn edges_from(&self, start: Coord) -> Vec<Edge> {
+        let mut out = vec![];
+        let mut seen: HashSet<Coord> = HashSet::new();
+        let mut queue: VecDeque<(Coord, u64, Keys)> = VecDeque::new();
+
+        seen.insert(start);
+        queue.push_back((start, 0, 0));
+
+        while let Some((coord, distance, doors)) = queue.pop_front() {
+            for (next, tile) in self.grid.neighbors(&coord) {
+                if tile == Tile::Wall || !seen.insert(next) {
+                    continue;
+                }
+
+                let next_doors = match tile {
+                    Tile::Door(d) => doors | (1 << d),
+                    _ => doors,
+                };
+
+                if let Tile::Key(key) = tile {
+                    out.push(Edge {
+                        key,
+                        coord: next,
+                        distance: distance + 1,
+                        doors: next_doors,
+                    });
+                }
+
+                queue.push_back((next, distance + 1, next_doors));
+            }
+        }
+
+        out
+    }
+
+    /// Fewest total steps for the robots starting at each entrance to collect
+    /// every key, or None if some key can't be reached.
+    ///
+    /// This is Dijkstra's algorithm over states of (robot positions, keys
+    /// held), where each move walks one robot to a key it doesn't have yet.
+    fn shortest_collection(&self) -> Option<u64> {
+        let all_keys = self.all_keys();
+
+        let mut edges: HashMap<Coord, Vec<Edge>> = HashMap::new();
+        let mut best: HashMap<(Vec<Coord>, Keys), u64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        let start = (self.entrances(), 0);
+        best.insert(start.clone(), 0);
+        queue.push(Reverse((0, start.0, start.1)));
+
+        while let Some(Reverse((distance, positions, keys))) = queue.pop() {
+            if keys == all_keys {
+                return Some(distance);
+            }
+
+            // Skip stale queue entries.
+            if distance > best[&(positions.clone(), keys)] {
+                continue;
+            }
+
+            for (robot, &position) in positions.iter().enumerate() {
+                let robot_edges = edges
+                    .entry(position)
+                    .or_insert_with(|| self.edges_from(position));
+
+                for edge in robot_edges.iter() {
+                    let held = keys & (1 << edge.key) != 0;
+                    let blocked = edge.doors & !keys != 0;
+                    if held || blocked {
+                        continue;
+                    }
+
+                    let mut next_positions = positions.clone();
+                    next_positions[robot] = edge.coord;
+                    let next_keys = keys | (1 << edge.key);
+                    let next_distance = distance + edge.distance;
+
+                    let state = (next_positions, next_keys);
+                    if best.get(&state).is_none_or(|&d| next_distance < d) {
+                        best.insert(state.clone(), next_distance);
+                        queue.push(Reverse((next_distance, state.0, state.1)));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Replace the area around the (single) entrance with four separate
+    /// vaults, each with its own entrance.
+    fn split(&self) -> Option<Vault> {
+        let (x, y) = match self.entrances()[..] {
+            [entrance] => entrance,
+            _ => return None,
+        };
+
+        let mut grid = self.grid.clone();
+        for &(dx, dy) in [(0, 0), (0, -1), (1, 0), (0, 1), (-1, 0)].iter() {
+            grid.set((x + dx, y + dy), Tile::Wall);
+        }
+        for &(dx, dy) in [(-1, -1), (1, -1), (1, 1), (-1, 1)].iter() {
+            grid.set((x + dx, y + dy), Tile::Entrance);
+        }
+
+        Some(Vault { grid })
+    }
+}
+
+impl FromStr for Vault {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(c) = s
+            .chars()
+            .find(|&c| c != '\n' && Tile::from_char(c).is_none())
+        {
+            return Err(SimpleError(format!("Invalid vault tile: {:?}", c)));
+        }
+
+        let grid = Grid::from_str_with(s.trim(), |c| Tile::from_char(c).unwrap())?;
+        Ok(Vault { grid })
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let vault = Vault::for_problem(18)?;
+
+    if part.includes(Part::One) {
+        match vault.shortest_collection() {
+            Some(steps) => println!("Steps to collect all keys: {}", steps),
+            None => return bail("Couldn't collect all keys"),
+        }
+    }
+
+    if part.includes(Part::Two) {
+        let split = match vault.split() {
+            Some(v) => v,
+            None => return bail("Expected exactly one entrance"),
+        };
+        match split.shortest_collection() {
+            Some(steps) => println!("Steps to collect all keys with 4 robots: {}", steps),
+            None => return bail("Couldn't collect all keys"),
+        }
+    }
+
+    Ok(())
+}
+
+mod tests {
+    #[allow(dead_code)]
+    fn shortest(s: &str) -> Option<u64> {
+        s.parse::<super::Vault>().unwrap().shortest_collection()
+    }
+
+    #[test]
+    fn test_small_examples() {
+        let ex1 = "
+#########
+#b.A.@.a#
+#########
+";
+        assert_eq!(shortest(ex1), Some(8));
+
+        let ex2 = "
+########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################
+";
+        assert_eq!(shortest(ex2), Some(86));
+
+        let ex3 = "
+########################
+#...............b.C.D.f#
+#.######################
+#.....@.a.B.c.d.A.e.F.g#
+########################
+";
+        assert_eq!(shortest(ex3), Some(132));
+    }
+
+    #[test]
+    fn test_split_vault() {
+        use super::Vault;
+
+        let vault: Vault = "
+#######
+#a.#Cd#
+##...##
+##.@.##
+##...##
+#cB#Ab#
+#######
+"
+        .parse()
+        .unwrap();
+
+        assert_eq!(vault.split().unwrap().shortest_collection(), Some(8));
+    }
+
+    #[test]
+    fn test_unreachable_key() {
+        let locked = "
+#######
+#@.A.a#
+#######
+";
+        assert_eq!(shortest(locked), None);
+    }
+
+    #[test]
+    fn test_invalid_tile() {
+        assert!("#@?#".parse::<super::Vault>().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+/*! --- Day 19: Tractor Beam ---
+
+A drone system (your puzzle input) reports whether a point is being pulled by
+the tractor beam. It takes two inputs, the X and Y coordinates of a point
+(which can't be negative), and outputs 0 if the point is stationary or 1 if
+it's being pulled. The beam's emitter is at (0, 0), and the beam spreads out
+away from it in a cone.
+
+Part 1: How many points are affected by the tractor beam in the 50x50 area
+closest to the emitter?
+
+Part 2: Find the 100x100 square closest to the emitter that fits entirely
+within the tractor beam. What value do you get if you take that square's
+closest point's X coordinate, multiply it by 10000, then add its Y
+coordinate?
+*/
+
+use crate::intcode::Program;
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
+
+type Coord = (u64, u64);
+
+const SCAN_SIZE: u64 = 50;
+const SQUARE_SIZE: u64 = 100;
+
+/// Give up looking for a square after this many rows.
+const MAX_ROWS: u64 = 100_000;
+
+/// Rows near the emitter can be empty. Assume that the beam's left edge in
+/// row y is no further right than x = MAX_EDGE_SLOPE * (y + 1).
+const MAX_EDGE_SLOPE: u64 = 10;
+
+/// Something that can say whether a point is in the tractor beam.
+trait Beam {
+    fn affected(&mut self, x: u64, y: u64) -> ProblemResult<bool>;
+}
+
+/// Beam measured by running the drone program once per point.
+struct Drone<'a> {
+    program: &'a Program,
+}
+
+impl Beam for Drone<'_> {
+    fn affected(&mut self, x: u64, y: u64) -> ProblemResult<bool> {
+        let outputs = self.program.run_io(vec![x as i64, y as i64])?;
+        match outputs[..] {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => bail(format!(
+                "Bad drone output for ({}, {}): {:?}",
+                x, y, outputs
+            )),
+        }
+    }
+}
+
+/// Count the affected points in the `size` x `size` area closest to the
+/// emitter.
+fn count_affected<B: Beam>(beam: &mut B, size: u64) -> ProblemResult<u64> {
+    let mut count = 0;
+    for y in 0..size {
+        for x in 0..size {
+            if beam.affected(x, y)? {
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Find the top-left corner of the `size` x `size` square closest to the
+/// emitter that fits in the beam.
+///
+/// Walks down the lower edge of the beam, treating each point on it as the
+/// bottom-left corner of a square. The first one whose top-right corner is
+/// inside the upper edge of the beam is the closest square.
+fn closest_square<B: Beam>(beam: &mut B, size: u64) -> ProblemResult<Coord> {
+    if size == 0 {
+        return bail("Square size must be positive");
+    }
+
+    // The left edge of the beam never moves left as y increases.
+    let mut left = 0;
+    for y in (size - 1)..MAX_ROWS {
+        let mut edge = None;
+        for x in left..=(MAX_EDGE_SLOPE * (y + 1)) {
+            if beam.affected(x, y)? {
+                edge = Some(x);
+                break;
+            }
+        }
+
+        let x = match edge {
+            Some(x) => x,
+            None => continue,
+        };
+        left = x;
+
+        let top = y + 1 - size;
+        if beam.affected(x + size - 1, top)? {
+            return Ok((x, top));
+        }
+    }
+
+    bail(format!(
+        "No {}x{} square found in the first {} rows",
+        size, size, MAX_ROWS
+    ))
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let program = Program::for_problem(19)?;
+    let mut drone = Drone { program: &program };
+
+    if part.includes(Part::One) {
+        let count = count_affected(&mut drone, SCAN_SIZE)?;
+        println!("Points affected in {0}x{0} area: {1}", SCAN_SIZE, count);
+    }
+
+    if part.includes(Part::Two) {
+        let (x, y) = closest_square(&mut drone, SQUARE_SIZE)?;
+        println!("Closest {0}x{0} square: ({1}, {2})", SQUARE_SIZE, x, y);
+        println!("Answer: {}", x * 10000 + y);
+    }
+
+    Ok(())
+}
+
+mod tests {
+    /// Beam between two lines through the emitter, each given as a slope
+    /// dy/dx. Points on either line are affected.
+    #[allow(dead_code)]
+    struct SlopeBeam {
+        steep: (u64, u64),
+        shallow: (u64, u64),
+    }
+
+    impl super::Beam for SlopeBeam {
+        fn affected(&mut self, x: u64, y: u64) -> crate::utils::ProblemResult<bool> {
+            let (steep_dy, steep_dx) = self.steep;
+            let (shallow_dy, shallow_dx) = self.shallow;
+            Ok(y * steep_dx <= steep_dy * x && y * shallow_dx >= shallow_dy * x)
+        }
+    }
+
+    #[test]
+    fn test_count_affected() {
+        use super::count_affected;
+
+        // Between y = x and y = 2x.
+        let mut beam = SlopeBeam {
+            steep: (2, 1),
+            shallow: (1, 1),
+        };
+        assert_eq!(count_affected(&mut beam, 5).unwrap(), 9);
+    }
+
+    #[test]
+    fn test_closest_square() {
+        use super::{closest_square, Beam};
+
+        let mut beam = SlopeBeam {
+            steep: (2, 1),
+            shallow: (1, 1),
+        };
+        assert_eq!(closest_square(&mut beam, 10).unwrap(), (18, 27));
+
+        // Check against every candidate square for a narrower beam, which has
+        // empty rows near the emitter.
+        let mut beam = SlopeBeam {
+            steep: (3, 4),
+            shallow: (1, 2),
+        };
+        for size in 1..=6 {
+            let mut fits = |x0: u64, y0: u64| {
+                (y0..y0 + size).all(|y| (x0..x0 + size).all(|x| beam.affected(x, y).unwrap()))
+            };
+            let expected = (0..200)
+                .flat_map(|y| (0..200).map(move |x| (x, y)))
+                .find(|&(x, y)| fits(x, y))
+                .unwrap();
+            assert_eq!(closest_square(&mut beam, size).unwrap(), expected);
+        }
+    }
+}
@@ -136,42 +136,66 @@
 /// the answer would be 1202.)
 use crate::intcode::Program;
 use crate::utils;
-use crate::utils::ProblemInput;
+use crate::utils::{Part, ProblemAnswers, ProblemInput};
 
-pub fn run() -> utils::ProblemResult<()> {
+pub fn solve(part: Part) -> utils::ProblemResult<ProblemAnswers> {
     let program = Program::for_problem(2)?;
+    let mut answers = ProblemAnswers::default();
 
-    println!("Problem 2:");
-    println!("==========");
+    if part.includes(Part::One) {
+        let result = program.run_problem2(12, 2, 0)?;
+        answers.part1 = Some(result.to_string());
+    }
 
-    println!("\nPart 1");
-    println!("------");
-    match program.run_problem2(12, 2, 0) {
-        Ok(result) => {
-            println!("Output Value at Index 0: {}", result);
-        }
-        Err(e) => {
-            println!("Error executing program: {:?}", e);
+    if part.includes(Part::Two) {
+        match find_noun_and_verb(&program, 19690720) {
+            Some((noun, verb)) => answers.part2 = Some((noun * 100 + verb).to_string()),
+            None => return utils::bail("Failed to find answer!"),
         }
     }
 
-    println!("\nPart 2");
-    println!("------");
+    Ok(answers)
+}
+
+/// Find the noun and verb that make the program output `target`.
+fn find_noun_and_verb(program: &Program, target: i64) -> Option<(i64, i64)> {
+    let mut memory = program.with_memory();
     for noun in 0..100 {
         for verb in 0..100 {
-            match program.run_problem2(noun, verb, 0) {
-                Ok(19690720) => {
-                    println!("Got target from noun={}, verb={}", noun, verb);
-                    println!("Answer is: {}", noun * 100 + verb);
-                    return Ok(());
-                }
-                Ok(_result) => {}
-                Err(e) => {
-                    println!("Error executing for noun={}, verb={}: {:?}", noun, verb, e);
+            // Some inputs make the program fail. Those can't be the answer.
+            let result = program.run_with_overrides_in(&mut memory, &[(1, noun), (2, verb)], 0);
+            if let Ok(value) = result {
+                if value == target {
+                    return Some((noun, verb));
                 }
             }
         }
     }
+    None
+}
+
+mod tests {
+    #[test]
+    fn test_known_answers() {
+        use crate::intcode::Program;
+        use crate::utils::ProblemInput;
+
+        let program = Program::for_problem(2).unwrap();
+        assert_eq!(program.run_problem2(12, 2, 0).unwrap(), 3101878);
+        assert_eq!(program.run_problem2(84, 44, 0).unwrap(), 19690720);
+    }
 
-    utils::bail("Failed to find answer!")
+    #[test]
+    fn test_solve() {
+        use super::solve;
+        use crate::utils::{Part, ProblemAnswers};
+
+        assert_eq!(
+            solve(Part::Both).unwrap(),
+            ProblemAnswers {
+                part1: Some("3101878".into()),
+                part2: Some("8444".into()),
+            }
+        );
+    }
 }
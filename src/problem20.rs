@@ -0,0 +1,399 @@
+/*! --- Day 20: Donut Maze ---
+
+The maze is shaped like a donut. Open tiles (.) are surrounded by walls (#),
+and pairs of two-letter labels next to open tiles on the inner and outer edges
+of the donut mark portals. Stepping onto a portal's tile and taking one more
+step moves you to the tile next to the other portal with the same label.
+
+Every maze has a start tile labeled AA and an end tile labeled ZZ.
+
+Part 1: How many steps does it take to get from AA to ZZ?
+
+Part 2: The maze is actually recursive. Inner portals lead one level deeper
+into a copy of the maze, and outer portals lead back up a level. At the
+outermost level, outer portals are walls, and AA and ZZ only exist at the
+outermost level. How many steps does it take to get from AA to ZZ at the
+outermost level?
+*/
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+use crate::grid::{Coord, Grid};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult, SimpleError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Tile {
+    #[default]
+    Wall,
+    Open,
+}
+
+impl From<Tile> for char {
+    fn from(tile: Tile) -> char {
+        match tile {
+            Tile::Wall => '#',
+            Tile::Open => '.',
+        }
+    }
+}
+
+const OFFSETS: [Coord; 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Where a portal leads, and how taking it changes the recursion level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Portal {
+    to: Coord,
+    level_change: i64,
+}
+
+#[derive(Debug, Clone)]
+struct Maze {
+    grid: Grid<Tile>,
+    portals: HashMap<Coord, Portal>,
+    start: Coord,
+    end: Coord,
+}
+
+impl Maze {
+    /// Fewest steps from AA to ZZ. If `recursive` is true, portals change the
+    /// recursion level as described in part 2.
+    fn shortest_path(&self, recursive: bool) -> Option<u64> {
+        // Cap the depth so that the search ends when there's no path.
+        //
+        // For each level k a shortest path goes below, pair the inner portal
+        // it last takes down from k with the outer portal it first takes back
+        // up to k. If levels j < k had the same pair, the part of the path
+        // between those portals at level k, moved up k - j levels, would be a
+        // shorter way between them at level j. Levels below the outermost all
+        // have the same portals, so moving it up is allowed. So the pairs are
+        // all different, and a shortest path never goes more than (number of
+        // portal pairs)^2 levels down. `portals` has an entry for each end of
+        // each pair.
+        let pairs = (self.portals.len() / 2) as i64;
+        let max_level = pairs * pairs;
+
+        let mut seen: HashSet<(Coord, i64)> = HashSet::new();
+        let mut queue: VecDeque<(Coord, i64, u64)> = VecDeque::new();
+        seen.insert((self.start, 0));
+        queue.push_back((self.start, 0, 0));
+
+        while let Some((coord, level, distance)) = queue.pop_front() {
+            if coord == self.end && level == 0 {
+                return Some(distance);
+            }
+
+            let mut next_states: Vec<(Coord, i64)> = OFFSETS
+                .iter()
+                .map(|&(dx, dy)| (coord.0 + dx, coord.1 + dy))
+                .filter(|next| self.grid.get(next) == Tile::Open)
+                .map(|next| (next, level))
+                .collect();
+
+            if let Some(portal) = self.portals.get(&coord) {
+                let next_level = if recursive {
+                    level + portal.level_change
+                } else {
+                    level
+                };
+                if (0..=max_level).contains(&next_level) {
+                    next_states.push((portal.to, next_level));
+                }
+            }
+
+            for state in next_states {
+                if seen.insert(state) {
+                    queue.push_back((state.0, state.1, distance + 1));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl FromStr for Maze {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars: HashMap<Coord, char> = HashMap::new();
+        for (y, line) in s.trim_start_matches('\n').lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                chars.insert((x as i64, y as i64), c);
+            }
+        }
+        let char_at = |c: Coord| *chars.get(&c).unwrap_or(&' ');
+
+        let mut grid = Grid::empty();
+        for (&coord, &c) in chars.iter() {
+            match c {
+                '.' => grid.set(coord, Tile::Open),
+                '#' => grid.set(coord, Tile::Wall),
+                _ => {}
+            }
+        }
+
+        let open: Vec<Coord> = grid
+            .iter()
+            .filter(|&(_, &tile)| tile == Tile::Open)
+            .map(|(&coord, _)| coord)
+            .collect();
+
+        let maze_coords = || chars.iter().filter(|&(_, &c)| c == '.' || c == '#');
+        let min_x = maze_coords().map(|(c, _)| c.0).min().unwrap_or(0);
+        let max_x = maze_coords().map(|(c, _)| c.0).max().unwrap_or(0);
+        let min_y = maze_coords().map(|(c, _)| c.1).min().unwrap_or(0);
+        let max_y = maze_coords().map(|(c, _)| c.1).max().unwrap_or(0);
+
+        // Find the label next to each portal tile. Labels read left-to-right
+        // or top-to-bottom, so the letter nearer the maze comes second when
+        // the label is above or to the left.
+        let mut labels: HashMap<String, Vec<Coord>> = HashMap::new();
+        for &coord in open.iter() {
+            for &(dx, dy) in OFFSETS.iter() {
+                let near = char_at((coord.0 + dx, coord.1 + dy));
+                let far = char_at((coord.0 + 2 * dx, coord.1 + 2 * dy));
+                if !near.is_ascii_uppercase() {
+                    continue;
+                }
+                if !far.is_ascii_uppercase() {
+                    return Err(SimpleError(format!("Incomplete label next to {:?}", coord)));
+                }
+
+                let label: String = if dx < 0 || dy < 0 {
+                    [far, near].iter().collect()
+                } else {
+                    [near, far].iter().collect()
+                };
+                labels.entry(label).or_default().push(coord);
+            }
+        }
+
+        let is_outer = |c: Coord| c.0 == min_x || c.0 == max_x || c.1 == min_y || c.1 == max_y;
+
+        let mut start = None;
+        let mut end = None;
+        let mut portals = HashMap::new();
+        for (label, coords) in labels.iter() {
+            match (label.as_ref(), &coords[..]) {
+                ("AA", &[c]) => start = Some(c),
+                ("ZZ", &[c]) => end = Some(c),
+                (_, &[a, b]) if label != "AA" && label != "ZZ" => {
+                    for &(from, to) in [(a, b), (b, a)].iter() {
+                        let level_change = if is_outer(from) { -1 } else { 1 };
+                        portals.insert(from, Portal { to, level_change });
+                    }
+                }
+                _ => {
+                    return Err(SimpleError(format!(
+                        "Label {} appears {} times",
+                        label,
+                        coords.len()
+                    )))
+                }
+            }
+        }
+
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(Maze {
+                grid,
+                portals,
+                start,
+                end,
+            }),
+            _ => Err(SimpleError("Maze must have AA and ZZ".into())),
+        }
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let maze = Maze::for_problem(20)?;
+
+    if part.includes(Part::One) {
+        match maze.shortest_path(false) {
+            Some(steps) => println!("Steps from AA to ZZ: {}", steps),
+            None => return bail("No path from AA to ZZ"),
+        }
+    }
+
+    if part.includes(Part::Two) {
+        match maze.shortest_path(true) {
+            Some(steps) => println!("Steps from AA to ZZ (recursive): {}", steps),
+            None => return bail("No recursive path from AA to ZZ"),
+        }
+    }
+
+    Ok(())
+}
+
+mod tests {
+    #[allow(dead_code)]
+    const EXAMPLE: &str = "
+         A
+         A
+  #######.#########
+  #######.........#
+  #######.#######.#
+  #######.#######.#
+  #######.#######.#
+  #####  B    ###.#
+BC...##  C    ###.#
+  ##.##       ###.#
+  ##...DE  F  ###.#
+  #####    G  ###.#
+  #########.#####.#
+DE..#######...###.#
+  #.#########.###.#
+FG..#########.....#
+  ###########.#####
+             Z
+             Z
+";
+
+    /// Larger example from part 1, which has no recursive path.
+    #[allow(dead_code)]
+    const LARGE_EXAMPLE: &str = "
+                   A
+                   A
+  #################.#############
+  #.#...#...................#.#.#
+  #.#.#.###.###.###.#########.#.#
+  #.#.#.......#...#.....#.#.#...#
+  #.#########.###.#####.#.#.###.#
+  #.............#.#.....#.......#
+  ###.###########.###.#.#.#.#.###
+  #.....#        A   C    #.#.#.#
+  #######        S   P    #####.#
+  #.#...#                 #......VT
+  #.#.#.#                 #.#####
+  #...#.#               YN....#.#
+  #.###.#                 #####.#
+DI....#.#                 #.....#
+  #####.#                 #.###.#
+ZZ......#               QG....#..AS
+  ###.###                 #######
+JO..#.#.#                 #.....#
+  #.#.#.#                 ###.#.#
+  #...#..DI             BU....#..LF
+  #####.#                 #.#####
+YN......#               VT..#....QG
+  #.###.#                 #.###.#
+  #.#...#                 #.....#
+  ###.###    J L     J    #.#.###
+  #.....#    O F     P    #.#...#
+  #.###.#####.#.#####.#####.###.#
+  #...#.#.#...#.....#.....#.#...#
+  #.#####.###.###.#.#.#########.#
+  #...#.#.....#...#.#.#.#.....#.#
+  #.###.#####.###.###.#.#.#######
+  #.#.........#...#.............#
+  #########.###.###.#############
+           B   J   C
+           U   P   P
+";
+
+    /// Example from part 2.
+    #[allow(dead_code)]
+    const RECURSIVE_EXAMPLE: &str = "
+             Z L X W       C
+             Z P Q B       K
+  ###########.#.#.#.#######.###############
+  #...#.......#.#.......#.#.......#.#.#...#
+  ###.#.#.#.#.#.#.#.###.#.#.#######.#.#.###
+  #.#...#.#.#...#.#.#...#...#...#.#.......#
+  #.###.#######.###.###.#.###.###.#.#######
+  #...#.......#.#...#...#.............#...#
+  #.#########.#######.#.#######.#######.###
+  #...#.#    F       R I       Z    #.#.#.#
+  #.###.#    D       E C       H    #.#.#.#
+  #.#...#                           #...#.#
+  #.###.#                           #.###.#
+  #.#....OA                       WB..#.#..ZH
+  #.###.#                           #.#.#.#
+CJ......#                           #.....#
+  #######                           #######
+  #.#....CK                         #......IC
+  #.###.#                           #.###.#
+  #.....#                           #...#.#
+  ###.###                           #.#.#.#
+XF....#.#                         RF..#.#.#
+  #####.#                           #######
+  #......CJ                       NM..#...#
+  ###.#.#                           #.###.#
+RE....#.#                           #......RF
+  ###.###        X   X       L      #.#.#.#
+  #.....#        F   Q       P      #.#.#.#
+  ###.###########.###.#######.#########.###
+  #.....#...#.....#.......#...#.....#.#...#
+  #####.#.###.#######.#######.###.###.#.#.#
+  #.......#.......#.#.#.#.#...#...#...#.#.#
+  #####.###.#####.#.#.#.#.###.###.#.###.###
+  #.......#.....#.#...#...............#...#
+  #############.#.#.###.###################
+               A O F   N
+               A A D   M
+";
+
+    #[test]
+    fn test_parse_portals() {
+        use super::{Maze, Portal};
+
+        let maze: Maze = EXAMPLE.parse().unwrap();
+        assert_eq!(maze.start, (9, 2));
+        assert_eq!(maze.end, (13, 16));
+        assert_eq!(maze.portals.len(), 6);
+
+        // BC, from the inner edge to the outer edge and back.
+        assert_eq!(
+            maze.portals[&(9, 6)],
+            Portal {
+                to: (2, 8),
+                level_change: 1
+            }
+        );
+        assert_eq!(
+            maze.portals[&(2, 8)],
+            Portal {
+                to: (9, 6),
+                level_change: -1
+            }
+        );
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        use super::Maze;
+
+        let maze: Maze = EXAMPLE.parse().unwrap();
+        assert_eq!(maze.shortest_path(false), Some(23));
+
+        let maze: Maze = LARGE_EXAMPLE.parse().unwrap();
+        assert_eq!(maze.shortest_path(false), Some(58));
+    }
+
+    #[test]
+    fn test_shortest_recursive_path() {
+        use super::Maze;
+
+        // Every portal path needs an outer portal at level 0, so the only way
+        // through is the direct route.
+        let maze: Maze = EXAMPLE.parse().unwrap();
+        assert_eq!(maze.shortest_path(true), Some(26));
+
+        // This one has to go 10 levels down and back.
+        let maze: Maze = RECURSIVE_EXAMPLE.parse().unwrap();
+        assert_eq!(maze.shortest_path(true), Some(396));
+
+        // The depth limit stops the search when there's no path.
+        let maze: Maze = LARGE_EXAMPLE.parse().unwrap();
+        assert_eq!(maze.shortest_path(true), None);
+    }
+
+    #[test]
+    fn test_unpaired_label() {
+        use super::Maze;
+
+        let bad = EXAMPLE.replace("FG..", "FH..");
+        assert!(bad.parse::<Maze>().is_err());
+    }
+}
@@ -0,0 +1,295 @@
+/*! --- Day 21: Springdroid Adventure ---
+
+A springdroid walks along the hull of the ship, and jumps over holes when told
+to. Its jumping logic is written in springscript, which is entered as ASCII
+into the droid's Intcode program (your puzzle input), one instruction per
+line.
+
+Springscript has two writable registers: T (temporary) and J (jump). Both
+start out false. Read-only registers hold the droid's sensor readings, which
+are true if there's ground that many tiles ahead: A (one tile), B (two
+tiles), C (three tiles) and D (four tiles). Jumping lands four tiles ahead.
+
+- AND X Y sets Y to true if both X and Y are true.
+- OR X Y sets Y to true if at least one of X or Y is true.
+- NOT X Y sets Y to true if X is false.
+
+At most 15 instructions can be entered. The program ends with WALK, after
+which the droid moves forward, jumping whenever J is true after running the
+script. If the droid falls into space, the program renders its last moments.
+Otherwise, it reports the amount of hull damage as a large, non-ASCII value.
+
+Part 1: What amount of hull damage does it report?
+
+Part 2: Ending the program with RUN instead of WALK gives extended sensor
+readings: E (five tiles), F, G, H and I (nine tiles). What amount of hull
+damage does it report?
+*/
+
+use std::error::Error;
+use std::fmt;
+
+use crate::intcode::{decode_ascii, Program};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
+
+/// Maximum number of instructions the droid's memory can hold.
+const MAX_INSTRUCTIONS: usize = 15;
+
+/// Registers are named by single letters.
+type Register = char;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    And,
+    Or,
+    Not,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::And => write!(f, "AND"),
+            Op::Or => write!(f, "OR"),
+            Op::Not => write!(f, "NOT"),
+        }
+    }
+}
+
+/// How the droid moves once the script is entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// Can a script run in this mode read `register`?
+    fn can_read(self, register: Register) -> bool {
+        match register {
+            'A'..='D' | 'T' | 'J' => true,
+            'E'..='I' => self == Mode::Run,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Walk => write!(f, "WALK"),
+            Mode::Run => write!(f, "RUN"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ScriptError {
+    TooManyInstructions,
+    UnknownRegister(Register),
+    ReadOnlyRegister(Register),
+    UnavailableSensor { register: Register, mode: Mode },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::TooManyInstructions => {
+                write!(
+                    f,
+                    "scripts can't have more than {} instructions",
+                    MAX_INSTRUCTIONS
+                )
+            }
+            ScriptError::UnknownRegister(r) => write!(f, "unknown register {}", r),
+            ScriptError::ReadOnlyRegister(r) => write!(f, "register {} is read-only", r),
+            ScriptError::UnavailableSensor { register, mode } => {
+                write!(f, "register {} can't be read in {} mode", register, mode)
+            }
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+/// Builder for springscript programs.
+#[derive(Debug, Default)]
+struct SpringScript {
+    instructions: Vec<(Op, Register, Register)>,
+}
+
+impl SpringScript {
+    fn new() -> SpringScript {
+        SpringScript::default()
+    }
+
+    fn and(&mut self, x: Register, y: Register) -> Result<&mut Self, ScriptError> {
+        self.push(Op::And, x, y)
+    }
+
+    fn or(&mut self, x: Register, y: Register) -> Result<&mut Self, ScriptError> {
+        self.push(Op::Or, x, y)
+    }
+
+    fn not(&mut self, x: Register, y: Register) -> Result<&mut Self, ScriptError> {
+        self.push(Op::Not, x, y)
+    }
+
+    fn push(&mut self, op: Op, x: Register, y: Register) -> Result<&mut Self, ScriptError> {
+        if self.instructions.len() == MAX_INSTRUCTIONS {
+            return Err(ScriptError::TooManyInstructions);
+        }
+        for &r in [x, y].iter() {
+            if !Mode::Run.can_read(r) {
+                return Err(ScriptError::UnknownRegister(r));
+            }
+        }
+        if y != 'T' && y != 'J' {
+            return Err(ScriptError::ReadOnlyRegister(y));
+        }
+
+        self.instructions.push((op, x, y));
+        Ok(self)
+    }
+
+    /// Encode the script as ASCII input, ending with `mode`.
+    fn encode(&self, mode: Mode) -> Result<Vec<i64>, ScriptError> {
+        let mut text = String::new();
+        for &(op, x, y) in self.instructions.iter() {
+            if !mode.can_read(x) {
+                return Err(ScriptError::UnavailableSensor { register: x, mode });
+            }
+            text.push_str(&format!("{} {} {}\n", op, x, y));
+        }
+        text.push_str(&format!("{}\n", mode));
+
+        Ok(text.bytes().map(i64::from).collect())
+    }
+}
+
+/// Run `script` on the droid and return the hull damage it reports.
+///
+/// If the droid falls into space, the error contains its rendering of what
+/// happened.
+fn survey_hull(program: &Program, script: &SpringScript, mode: Mode) -> ProblemResult<i64> {
+    let outputs = program.run_io(script.encode(mode)?)?;
+    let (text, others) = decode_ascii(&outputs);
+    match others.last() {
+        Some(&damage) => Ok(damage),
+        None => bail(format!("Droid didn't make it across:\n{}", text)),
+    }
+}
+
+/// Jump if there's a hole in the next three tiles and ground to land on.
+fn walk_script() -> Result<SpringScript, ScriptError> {
+    let mut script = SpringScript::new();
+    script
+        .not('A', 'J')?
+        .not('B', 'T')?
+        .or('T', 'J')?
+        .not('C', 'T')?
+        .or('T', 'J')?
+        .and('D', 'J')?;
+    Ok(script)
+}
+
+/// Like `walk_script`, but only jump if the droid can either take a step or
+/// jump again after landing.
+fn run_script() -> Result<SpringScript, ScriptError> {
+    let mut script = walk_script()?;
+    script
+        .not('E', 'T')?
+        .not('T', 'T')?
+        .or('H', 'T')?
+        .and('T', 'J')?;
+    Ok(script)
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let program = Program::for_problem(21)?;
+
+    if part.includes(Part::One) {
+        let damage = survey_hull(&program, &walk_script()?, Mode::Walk)?;
+        println!("Hull damage walking: {}", damage);
+    }
+
+    if part.includes(Part::Two) {
+        let damage = survey_hull(&program, &run_script()?, Mode::Run)?;
+        println!("Hull damage running: {}", damage);
+    }
+
+    Ok(())
+}
+
+mod tests {
+    #[test]
+    fn test_encode() {
+        use super::{Mode, SpringScript};
+
+        let mut script = SpringScript::new();
+        script.not('A', 'J').unwrap().and('D', 'J').unwrap();
+
+        let expected: Vec<i64> = "NOT A J\nAND D J\nWALK\n".bytes().map(i64::from).collect();
+        assert_eq!(script.encode(Mode::Walk).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_too_many_instructions() {
+        use super::{ScriptError, SpringScript};
+
+        let mut script = SpringScript::new();
+        for _ in 0..15 {
+            script.or('A', 'J').unwrap();
+        }
+        assert_eq!(
+            script.or('A', 'J').unwrap_err(),
+            ScriptError::TooManyInstructions
+        );
+        assert_eq!(script.instructions.len(), 15);
+    }
+
+    #[test]
+    fn test_register_usage() {
+        use super::{Mode, ScriptError, SpringScript};
+
+        let mut script = SpringScript::new();
+        assert_eq!(
+            script.not('A', 'B').unwrap_err(),
+            ScriptError::ReadOnlyRegister('B')
+        );
+        assert_eq!(
+            script.not('X', 'J').unwrap_err(),
+            ScriptError::UnknownRegister('X')
+        );
+
+        // Extended sensors are only available when running.
+        script.or('H', 'J').unwrap();
+        assert!(script.encode(Mode::Run).is_ok());
+        assert_eq!(
+            script.encode(Mode::Walk).unwrap_err(),
+            ScriptError::UnavailableSensor {
+                register: 'H',
+                mode: Mode::Walk
+            }
+        );
+    }
+
+    #[test]
+    fn test_survey_hull() {
+        use super::{survey_hull, walk_script, Mode};
+        use crate::intcode::Program;
+
+        // Read one character of input, then report 1234 damage if it's 'N'.
+        let program: Program = "3,100,1008,100,78,101,1005,101,12,104,33,99,104,1234,99"
+            .parse()
+            .unwrap();
+        let script = walk_script().unwrap();
+        assert_eq!(survey_hull(&program, &script, Mode::Walk).unwrap(), 1234);
+
+        // Otherwise, print '!' and halt.
+        let program: Program = "3,100,1008,100,65,101,1005,101,12,104,33,99,104,1234,99"
+            .parse()
+            .unwrap();
+        let err = survey_hull(&program, &script, Mode::Walk).unwrap_err();
+        assert_eq!(err.to_string(), "Droid didn't make it across:\n!");
+    }
+}
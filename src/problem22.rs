@@ -0,0 +1,270 @@
+/*! --- Day 22: Slam Shuffle ---
+
+A deck of space cards is shuffled with three techniques:
+
+- "deal into new stack" reverses the order of the deck.
+- "cut N" moves the top N cards to the bottom of the deck. If N is negative,
+  the bottom |N| cards move to the top instead.
+- "deal with increment N" deals the cards onto a table, leaving N - 1 empty
+  spaces between each card and wrapping around, then collects them in order.
+
+Part 1: After shuffling a deck of 10007 cards, what is the position of card
+2019?
+
+Part 2: Using a deck of 119315717514047 cards, and applying the shuffle
+101741582076661 times in a row, what number is on the card that ends up in
+position 2020?
+*/
+
+use std::str::FromStr;
+
+use crate::utils::{Part, ProblemInput, ProblemResult, SimpleError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Technique {
+    NewStack,
+    Cut(i128),
+    Increment(i128),
+}
+
+impl FromStr for Technique {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_arg = |arg: &str| {
+            arg.parse::<i128>()
+                .map_err(|_| SimpleError(format!("Invalid shuffle argument: {:?}", arg)))
+        };
+
+        if s == "deal into new stack" {
+            Ok(Technique::NewStack)
+        } else if let Some(arg) = s.strip_prefix("cut ") {
+            Ok(Technique::Cut(parse_arg(arg)?))
+        } else if let Some(arg) = s.strip_prefix("deal with increment ") {
+            Ok(Technique::Increment(parse_arg(arg)?))
+        } else {
+            Err(SimpleError(format!("Unknown shuffle technique: {:?}", s)))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Shuffle {
+    techniques: Vec<Technique>,
+}
+
+impl Shuffle {
+    /// The shuffle as a function from a card's position before shuffling to
+    /// its position after shuffling.
+    fn as_linear_map(&self, deck_size: i128) -> LinearMap {
+        self.techniques
+            .iter()
+            .fold(LinearMap::identity(deck_size), |map, &technique| {
+                let (a, b) = match technique {
+                    Technique::NewStack => (-1, -1),
+                    Technique::Cut(n) => (1, -n),
+                    Technique::Increment(n) => (n, 0),
+                };
+                map.then(&LinearMap::new(a, b, deck_size))
+            })
+    }
+
+    /// The order of cards in a deck of `deck_size` cards after shuffling.
+    #[allow(dead_code)]
+    fn deal(&self, deck_size: i128) -> Vec<i128> {
+        let map = self.as_linear_map(deck_size);
+        let mut deck = vec![0; deck_size as usize];
+        for card in 0..deck_size {
+            deck[map.apply(card) as usize] = card;
+        }
+        deck
+    }
+}
+
+impl FromStr for Shuffle {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let techniques: Result<Vec<Technique>, SimpleError> = s
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.parse())
+            .collect();
+
+        Ok(Shuffle {
+            techniques: techniques?,
+        })
+    }
+}
+
+/// The function `x -> a * x + b (mod modulus)`.
+///
+/// `modulus` must be prime for `invert` to work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LinearMap {
+    a: i128,
+    b: i128,
+    modulus: i128,
+}
+
+impl LinearMap {
+    fn new(a: i128, b: i128, modulus: i128) -> LinearMap {
+        LinearMap {
+            a: a.rem_euclid(modulus),
+            b: b.rem_euclid(modulus),
+            modulus,
+        }
+    }
+
+    fn identity(modulus: i128) -> LinearMap {
+        LinearMap::new(1, 0, modulus)
+    }
+
+    fn apply(&self, x: i128) -> i128 {
+        (self.a * x + self.b).rem_euclid(self.modulus)
+    }
+
+    /// The function that applies `self`, then `other`.
+    fn then(&self, other: &LinearMap) -> LinearMap {
+        LinearMap::new(other.a * self.a, other.a * self.b + other.b, self.modulus)
+    }
+
+    /// `self` applied `n` times, computed by repeated squaring.
+    fn repeat(&self, mut n: u64) -> LinearMap {
+        let mut result = LinearMap::identity(self.modulus);
+        let mut square = *self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            n >>= 1;
+        }
+        result
+    }
+
+    /// The inverse function, `y -> (y - b) / a`.
+    fn invert(&self) -> LinearMap {
+        // By Fermat's little theorem, a^(p - 2) is the inverse of a mod p.
+        let a_inv = mod_pow(self.a, self.modulus - 2, self.modulus);
+        LinearMap::new(a_inv, -self.b * a_inv, self.modulus)
+    }
+}
+
+fn mod_pow(mut base: i128, mut exp: i128, modulus: i128) -> i128 {
+    let mut result = 1;
+    base = base.rem_euclid(modulus);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    result
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let shuffle = Shuffle::for_problem(22)?;
+
+    if part.includes(Part::One) {
+        let map = shuffle.as_linear_map(10007);
+        println!("Position of card 2019: {}", map.apply(2019));
+    }
+
+    if part.includes(Part::Two) {
+        const DECK_SIZE: i128 = 119_315_717_514_047;
+        const REPEATS: u64 = 101_741_582_076_661;
+
+        let map = shuffle.as_linear_map(DECK_SIZE).repeat(REPEATS);
+        println!("Card at position 2020: {}", map.invert().apply(2020));
+    }
+
+    Ok(())
+}
+
+mod tests {
+    #[allow(dead_code)]
+    fn deal(s: &str) -> Vec<i128> {
+        s.parse::<super::Shuffle>().unwrap().deal(10)
+    }
+
+    #[test]
+    fn test_single_techniques() {
+        assert_eq!(
+            deal("deal into new stack"),
+            vec![9, 8, 7, 6, 5, 4, 3, 2, 1, 0]
+        );
+        assert_eq!(deal("cut 3"), vec![3, 4, 5, 6, 7, 8, 9, 0, 1, 2]);
+        assert_eq!(deal("cut -4"), vec![6, 7, 8, 9, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            deal("deal with increment 3"),
+            vec![0, 7, 4, 1, 8, 5, 2, 9, 6, 3]
+        );
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(
+            deal(
+                "deal with increment 7
+                 deal into new stack
+                 deal into new stack"
+            ),
+            vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]
+        );
+        assert_eq!(
+            deal(
+                "cut 6
+                 deal with increment 7
+                 deal into new stack"
+            ),
+            vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]
+        );
+        assert_eq!(
+            deal(
+                "deal with increment 7
+                 deal with increment 9
+                 cut -2"
+            ),
+            vec![6, 3, 0, 7, 4, 1, 8, 5, 2, 9]
+        );
+        assert_eq!(
+            deal(
+                "deal into new stack
+                 cut -2
+                 deal with increment 7
+                 cut 8
+                 cut -4
+                 deal with increment 7
+                 cut 3
+                 deal with increment 9
+                 deal with increment 3
+                 cut -1"
+            ),
+            vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]
+        );
+    }
+
+    #[test]
+    fn test_repeat_and_invert() {
+        use super::LinearMap;
+
+        let map = LinearMap::new(7, 3, 10007);
+
+        let mut x = 2019;
+        for _ in 0..100 {
+            x = map.apply(x);
+        }
+        assert_eq!(map.repeat(100).apply(2019), x);
+
+        let inverse = map.repeat(100).invert();
+        assert_eq!(inverse.apply(x), 2019);
+    }
+
+    #[test]
+    fn test_unknown_technique() {
+        assert!("deal with a smile".parse::<super::Shuffle>().is_err());
+    }
+}
@@ -0,0 +1,334 @@
+/*! --- Day 23: Category Six ---
+
+The droids have built a network of 50 computers, each running a copy of the
+same Network Interface Controller (NIC) Intcode program (your puzzle input).
+
+When each computer boots up, it requests its network address (0 through 49)
+as its first input. Packets consist of two values, X and Y, and are sent as
+three outputs: the destination address, then X, then Y. Received packets are
+read as two inputs, X then Y. If a computer's packet queue is empty when it
+requests input, it receives -1 instead.
+
+Part 1: What is the Y value of the first packet sent to address 255?
+
+Part 2: Address 255 is the NAT. It remembers only the last packet sent to it.
+When every computer has an empty incoming packet queue and isn't sending
+packets, the network is idle, and the NAT sends its packet to address 0. What
+is the first Y value delivered by the NAT to address 0 twice in a row?
+*/
+
+use std::collections::VecDeque;
+
+use crate::intcode::{ExecuteResult, Machine, Program};
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
+
+const NUM_COMPUTERS: usize = 50;
+const NAT_ADDRESS: i64 = 255;
+
+/// A computer attached to the network.
+trait Nic {
+    /// Add a value to the end of the computer's incoming packet queue.
+    fn deliver(&mut self, value: i64);
+
+    /// Let the computer run until it wants input that isn't available, and
+    /// return everything it output.
+    fn poll(&mut self) -> ExecuteResult<Vec<i64>>;
+
+    /// Does the computer have packets it hasn't read yet?
+    fn has_pending_input(&self) -> bool;
+}
+
+/// A NIC running the Intcode program.
+struct IntcodeNic {
+    machine: Machine,
+    queue: VecDeque<i64>,
+}
+
+impl IntcodeNic {
+    fn new(program: &Program, address: i64) -> IntcodeNic {
+        let mut machine = Machine::new(program);
+        machine.provide_input(address);
+        IntcodeNic {
+            machine,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Nic for IntcodeNic {
+    fn deliver(&mut self, value: i64) {
+        self.queue.push_back(value);
+    }
+
+    fn poll(&mut self) -> ExecuteResult<Vec<i64>> {
+        if self.machine.is_awaiting_input() {
+            if self.queue.is_empty() {
+                self.machine.provide_input(-1);
+            }
+            for value in self.queue.drain(..) {
+                self.machine.provide_input(value);
+            }
+        }
+
+        let mut out = vec![];
+        while let Some(value) = self.machine.run_until_output()? {
+            out.push(value);
+        }
+        Ok(out)
+    }
+
+    fn has_pending_input(&self) -> bool {
+        !self.queue.is_empty()
+    }
+}
+
+/// The NAT at address 255.
+#[derive(Debug, Default)]
+struct Nat {
+    /// The last packet sent to the NAT.
+    packet: Option<(i64, i64)>,
+    /// The Y value of the last packet the NAT sent to address 0.
+    last_sent_y: Option<i64>,
+}
+
+impl Nat {
+    fn receive(&mut self, x: i64, y: i64) {
+        self.packet = Some((x, y));
+    }
+
+    /// The packet to send to address 0 when the network goes idle, and
+    /// whether its Y value is the same as the previous packet the NAT sent.
+    fn wake(&mut self) -> Option<((i64, i64), bool)> {
+        let (x, y) = self.packet?;
+        let repeated = self.last_sent_y == Some(y);
+        self.last_sent_y = Some(y);
+        Some(((x, y), repeated))
+    }
+}
+
+/// How long the network should run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunUntil {
+    /// Stop when the first packet is sent to the NAT. This is enough for
+    /// part 1.
+    FirstNatPacket,
+    /// Stop when the NAT sends the same Y value twice in a row.
+    RepeatedWake,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NetworkResult {
+    /// Y value of the first packet sent to the NAT.
+    first_nat_y: i64,
+    /// First Y value sent by the NAT to address 0 twice in a row, if the
+    /// network ran that long.
+    repeated_wake_y: Option<i64>,
+}
+
+struct Network<N: Nic> {
+    nics: Vec<N>,
+    /// Output from each NIC that doesn't make up a full packet yet.
+    partial_packets: Vec<Vec<i64>>,
+    nat: Nat,
+}
+
+impl<N: Nic> Network<N> {
+    fn new(nics: Vec<N>) -> Network<N> {
+        let partial_packets = vec![vec![]; nics.len()];
+        Network {
+            nics,
+            partial_packets,
+            nat: Nat::default(),
+        }
+    }
+
+    /// Route packets between the NICs until the point given by `until`.
+    fn run(&mut self, until: RunUntil) -> ProblemResult<NetworkResult> {
+        let mut first_nat_y = None;
+
+        loop {
+            let mut sent_packet = false;
+
+            for i in 0..self.nics.len() {
+                let outputs = self.nics[i].poll()?;
+                self.partial_packets[i].extend(outputs);
+
+                let num_complete = self.partial_packets[i].len() / 3 * 3;
+                let packets: Vec<i64> = self.partial_packets[i].drain(..num_complete).collect();
+
+                for packet in packets.chunks(3) {
+                    let (address, x, y) = (packet[0], packet[1], packet[2]);
+                    sent_packet = true;
+
+                    if address == NAT_ADDRESS {
+                        if until == RunUntil::FirstNatPacket {
+                            return Ok(NetworkResult {
+                                first_nat_y: y,
+                                repeated_wake_y: None,
+                            });
+                        }
+                        first_nat_y.get_or_insert(y);
+                        self.nat.receive(x, y);
+                    } else {
+                        match self.nics.get_mut(address as usize) {
+                            Some(nic) if address >= 0 => {
+                                nic.deliver(x);
+                                nic.deliver(y);
+                            }
+                            _ => return bail(format!("Packet sent to bad address {}", address)),
+                        }
+                    }
+                }
+            }
+
+            let idle = !sent_packet && self.nics.iter().all(|nic| !nic.has_pending_input());
+            if !idle {
+                continue;
+            }
+
+            match (self.nat.wake(), first_nat_y) {
+                (Some(((x, y), repeated)), Some(first_nat_y)) => {
+                    if repeated {
+                        return Ok(NetworkResult {
+                            first_nat_y,
+                            repeated_wake_y: Some(y),
+                        });
+                    }
+                    self.nics[0].deliver(x);
+                    self.nics[0].deliver(y);
+                }
+                _ => return bail("Network went idle before any packets were sent to the NAT"),
+            }
+        }
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let program = Program::for_problem(23)?;
+
+    let nics = (0..NUM_COMPUTERS)
+        .map(|address| IntcodeNic::new(&program, address as i64))
+        .collect();
+    let until = if part == Part::One {
+        RunUntil::FirstNatPacket
+    } else {
+        RunUntil::RepeatedWake
+    };
+    let result = Network::new(nics).run(until)?;
+
+    if part.includes(Part::One) {
+        println!("First Y value sent to 255: {}", result.first_nat_y);
+    }
+
+    if let Some(y) = result.repeated_wake_y {
+        println!("First Y value sent twice by the NAT: {}", y);
+    }
+
+    Ok(())
+}
+
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::intcode::ExecuteResult;
+
+    /// A NIC that sends `initial` on its first poll, then forwards each
+    /// packet it receives to the NAT with its Y value halved.
+    #[allow(dead_code)]
+    struct HalvingNic {
+        initial: Vec<i64>,
+        queue: VecDeque<i64>,
+    }
+
+    impl super::Nic for HalvingNic {
+        fn deliver(&mut self, value: i64) {
+            self.queue.push_back(value);
+        }
+
+        fn poll(&mut self) -> ExecuteResult<Vec<i64>> {
+            let mut out: Vec<i64> = self.initial.drain(..).collect();
+            while self.queue.len() >= 2 {
+                let x = self.queue.pop_front().unwrap();
+                let y = self.queue.pop_front().unwrap();
+                out.extend(vec![super::NAT_ADDRESS, x, y / 2]);
+            }
+            Ok(out)
+        }
+
+        fn has_pending_input(&self) -> bool {
+            !self.queue.is_empty()
+        }
+    }
+
+    #[allow(dead_code)]
+    fn halving_nic(initial: Vec<i64>) -> HalvingNic {
+        HalvingNic {
+            initial,
+            queue: VecDeque::new(),
+        }
+    }
+
+    #[test]
+    fn test_nat_wake() {
+        use super::Nat;
+
+        let mut nat = Nat::default();
+        assert_eq!(nat.wake(), None);
+
+        nat.receive(1, 2);
+        nat.receive(3, 4);
+        assert_eq!(nat.wake(), Some(((3, 4), false)));
+        assert_eq!(nat.wake(), Some(((3, 4), true)));
+
+        nat.receive(5, 6);
+        assert_eq!(nat.wake(), Some(((5, 6), false)));
+    }
+
+    #[test]
+    fn test_idle_detection() {
+        use super::{Network, NetworkResult, RunUntil};
+
+        // NIC 1 forwards a packet to NIC 0 and then does nothing. NIC 0 sends
+        // it on to the NAT, halving Y each time it passes through, so the NAT
+        // sends 40, 20, 10, 5, 2, 1, 0, and then 0 again.
+        let nics = vec![halving_nic(vec![]), halving_nic(vec![0, 7, 80])];
+
+        let result = Network::new(nics).run(RunUntil::RepeatedWake).unwrap();
+        assert_eq!(
+            result,
+            NetworkResult {
+                first_nat_y: 40,
+                repeated_wake_y: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_stop_at_first_nat_packet() {
+        use super::{Network, NetworkResult, RunUntil};
+
+        let nics = vec![halving_nic(vec![]), halving_nic(vec![0, 7, 80])];
+        let mut network = Network::new(nics);
+
+        let result = network.run(RunUntil::FirstNatPacket).unwrap();
+        assert_eq!(
+            result,
+            NetworkResult {
+                first_nat_y: 40,
+                repeated_wake_y: None,
+            }
+        );
+
+        // The NAT was never woken.
+        assert_eq!(network.nat.packet, None);
+        assert_eq!(network.nat.last_sent_y, None);
+    }
+
+    #[test]
+    fn test_idle_without_nat_packet() {
+        use super::{Network, RunUntil};
+
+        let nics = vec![halving_nic(vec![]), halving_nic(vec![])];
+        assert!(Network::new(nics).run(RunUntil::RepeatedWake).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+/*! --- Day 24: Planet of Discord ---
+
+Eris is covered in bugs. Your scan of the area (your puzzle input) is a 5x5
+grid, where each tile is either a bug (#) or empty (.). Each minute, the bugs
+live and die based on the number of bugs in the four adjacent tiles:
+
+- A bug dies (becoming an empty space) unless there is exactly one bug
+  adjacent to it.
+- An empty space becomes infested with a bug if exactly one or two bugs are
+  adjacent to it.
+
+All tiles update at the same time. Tiles beyond the edge of the grid count as
+empty.
+
+Part 1: What is the biodiversity rating for the first layout that appears
+twice? Each tile is worth biodiversity points equal to increasing powers of
+two, reading left to right and top to bottom: 1, 2, 4, 8, 16, 32, and so on.
+
+Part 2: The grid is actually recursive. The middle tile of each grid is
+another 5x5 grid, and each grid is the middle tile of a larger one. Tiles on
+the edge of a grid are adjacent to the tiles around the middle tile of the
+grid containing it, and the tiles around the middle tile are adjacent to
+every tile on the matching edge of the grid inside it. Starting with your
+scan as the only grid with bugs, how many bugs are present after 200
+minutes?
+*/
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
+
+const SIZE: usize = 5;
+const CELLS: usize = SIZE * SIZE;
+
+/// Index of the middle tile, which holds the next level in when the grid is
+/// recursive.
+const CENTER: usize = CELLS / 2;
+
+/// Mask of tiles with bugs in a grid. Bit `row * 5 + col` is set if there's
+/// a bug at that location.
+type Level = u32;
+
+fn bit(row: usize, col: usize) -> Level {
+    1 << (row * SIZE + col)
+}
+
+/// Mask of the tiles adjacent to `i` in the same grid.
+fn flat_neighbors(i: usize) -> Level {
+    let (row, col) = (i / SIZE, i % SIZE);
+    let mut mask = 0;
+    if row > 0 {
+        mask |= bit(row - 1, col);
+    }
+    if row < SIZE - 1 {
+        mask |= bit(row + 1, col);
+    }
+    if col > 0 {
+        mask |= bit(row, col - 1);
+    }
+    if col < SIZE - 1 {
+        mask |= bit(row, col + 1);
+    }
+    mask
+}
+
+/// Mask of the tiles adjacent to `i` in the grid containing it.
+fn outer_neighbors(i: usize) -> Level {
+    let (row, col) = (i / SIZE, i % SIZE);
+    let mid = SIZE / 2;
+    let mut mask = 0;
+    if row == 0 {
+        mask |= bit(mid - 1, mid);
+    }
+    if row == SIZE - 1 {
+        mask |= bit(mid + 1, mid);
+    }
+    if col == 0 {
+        mask |= bit(mid, mid - 1);
+    }
+    if col == SIZE - 1 {
+        mask |= bit(mid, mid + 1);
+    }
+    mask
+}
+
+/// Mask of the tiles adjacent to `i` in the grid inside its middle tile.
+fn inner_neighbors(i: usize) -> Level {
+    let (row, col) = (i / SIZE, i % SIZE);
+    let mid = SIZE / 2;
+    let edge = |k| {
+        if (row, col) == (mid - 1, mid) {
+            bit(0, k)
+        } else if (row, col) == (mid + 1, mid) {
+            bit(SIZE - 1, k)
+        } else if (row, col) == (mid, mid - 1) {
+            bit(k, 0)
+        } else if (row, col) == (mid, mid + 1) {
+            bit(k, SIZE - 1)
+        } else {
+            0
+        }
+    };
+    (0..SIZE).map(edge).fold(0, |acc, b| acc | b)
+}
+
+/// Does a tile have a bug next minute, given whether it has one now and the
+/// number of adjacent bugs?
+fn lives(bug: bool, neighbors: u32) -> bool {
+    neighbors == 1 || (!bug && neighbors == 2)
+}
+
+/// A single, non-recursive grid of bugs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Eris(Level);
+
+impl Eris {
+    fn biodiversity(&self) -> u32 {
+        self.0
+    }
+
+    fn step(&self) -> Eris {
+        let mut next = 0;
+        for i in 0..CELLS {
+            let bug = self.0 & (1 << i) != 0;
+            if lives(bug, (self.0 & flat_neighbors(i)).count_ones()) {
+                next |= 1 << i;
+            }
+        }
+        Eris(next)
+    }
+
+    /// Step until a layout appears for the second time, and return it.
+    fn first_repeat(&self) -> Eris {
+        let mut seen = HashSet::new();
+        let mut current = *self;
+        while seen.insert(current) {
+            current = current.step();
+        }
+        current
+    }
+}
+
+impl FromStr for Eris {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.trim().lines().collect();
+        if lines.len() != SIZE {
+            return bail(format!("Expected {} rows, got {}", SIZE, lines.len()));
+        }
+
+        let mut level = 0;
+        for (row, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.chars().count() != SIZE {
+                return bail(format!("Expected {} columns, got {:?}", SIZE, line));
+            }
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '#' => level |= bit(row, col),
+                    '.' => {}
+                    _ => return bail(format!("Bad tile: {:?}", c)),
+                }
+            }
+        }
+
+        Ok(Eris(level))
+    }
+}
+
+/// Stack of recursive grids. Each level is the middle tile of the one before
+/// it.
+#[derive(Debug, Clone)]
+struct RecursiveEris {
+    levels: Vec<Level>,
+}
+
+impl RecursiveEris {
+    fn new(start: Eris) -> RecursiveEris {
+        RecursiveEris {
+            levels: vec![start.0 & !(1 << CENTER)],
+        }
+    }
+
+    fn num_bugs(&self) -> u32 {
+        self.levels.iter().map(|l| l.count_ones()).sum()
+    }
+
+    fn step(&self) -> RecursiveEris {
+        // Bugs can spread at most one level further in or out each minute,
+        // so pad with an empty level on each side.
+        let mut padded = vec![0];
+        padded.extend(self.levels.iter().copied());
+        padded.push(0);
+
+        let level_at = |depth: usize| padded.get(depth).copied().unwrap_or(0);
+
+        let mut levels: Vec<Level> = (0..padded.len())
+            .map(|depth| {
+                let outer = if depth == 0 { 0 } else { level_at(depth - 1) };
+                let (this, inner) = (level_at(depth), level_at(depth + 1));
+
+                let mut next = 0;
+                for i in (0..CELLS).filter(|&i| i != CENTER) {
+                    let neighbors = (this & flat_neighbors(i)).count_ones()
+                        + (outer & outer_neighbors(i)).count_ones()
+                        + (inner & inner_neighbors(i)).count_ones();
+                    if lives(this & (1 << i) != 0, neighbors) {
+                        next |= 1 << i;
+                    }
+                }
+                next
+            })
+            .collect();
+
+        // Drop empty padding so that the stack doesn't grow without bound.
+        if levels.last() == Some(&0) {
+            levels.pop();
+        }
+        if levels.first() == Some(&0) && levels.len() > 1 {
+            levels.remove(0);
+        }
+
+        RecursiveEris { levels }
+    }
+
+    fn step_n(&self, n: usize) -> RecursiveEris {
+        (0..n).fold(self.clone(), |eris, _| eris.step())
+    }
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    let eris = Eris::for_problem(24)?;
+
+    if part.includes(Part::One) {
+        let repeat = eris.first_repeat();
+        println!("First repeated biodiversity: {}", repeat.biodiversity());
+    }
+
+    if part.includes(Part::Two) {
+        let bugs = RecursiveEris::new(eris).step_n(200).num_bugs();
+        println!("Bugs after 200 minutes: {}", bugs);
+    }
+
+    Ok(())
+}
+
+mod tests {
+    #[allow(dead_code)]
+    const EXAMPLE: &str = "
+....#
+#..#.
+#..##
+..#..
+#....";
+
+    #[test]
+    fn test_first_repeat() {
+        use super::Eris;
+
+        let eris: Eris = EXAMPLE.parse().unwrap();
+        let expected: Eris = "
+.....
+.....
+.....
+#....
+.#...
+"
+        .parse()
+        .unwrap();
+
+        assert_eq!(eris.first_repeat(), expected);
+        assert_eq!(expected.biodiversity(), 2129920);
+    }
+
+    #[test]
+    fn test_recursive() {
+        use super::{Eris, RecursiveEris};
+
+        let eris: Eris = EXAMPLE.parse().unwrap();
+        let after = RecursiveEris::new(eris).step_n(10);
+        assert_eq!(after.num_bugs(), 99);
+
+        // Levels -5 through 5.
+        assert_eq!(after.levels.len(), 11);
+    }
+
+    #[test]
+    fn test_neighbor_counts() {
+        use super::{flat_neighbors, inner_neighbors, outer_neighbors};
+
+        // Every tile but the middle one has four neighbors in the recursive
+        // grid, and the tiles around the middle have eight.
+        for i in (0..25).filter(|&i| i != 12) {
+            let count = (flat_neighbors(i) & !(1 << 12)).count_ones()
+                + outer_neighbors(i).count_ones()
+                + inner_neighbors(i).count_ones();
+            let expected = if [7, 11, 13, 17].contains(&i) { 8 } else { 4 };
+            assert_eq!(count, expected, "tile {}", i);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use super::Eris;
+
+        assert!("....\n....\n....\n....\n....".parse::<Eris>().is_err());
+        assert!(".....\n.....\n..?..\n.....\n.....".parse::<Eris>().is_err());
+        assert!(".....\n.....\n.....".parse::<Eris>().is_err());
+    }
+}
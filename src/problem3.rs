@@ -103,20 +103,20 @@
 use std::str;
 
 use crate::utils;
-use crate::utils::ProblemInput;
+use crate::utils::Part;
 
 mod wire {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
     use std::error::Error;
     use std::fmt;
     use std::str::FromStr;
 
+    use crate::grid::{Coord, Direction};
+
     #[derive(Debug, Clone, Copy)]
-    enum Segment {
-        Up(u64),
-        Down(u64),
-        Left(u64),
-        Right(u64),
+    struct Segment {
+        direction: Direction,
+        length: u64,
     }
 
     #[derive(Debug)]
@@ -139,59 +139,58 @@ mod wire {
         type Err = ParseError;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let parse_length = || -> Result<u64, ParseError> {
-                let suffix = &s[1..];
-                let result = suffix.parse::<u64>();
-                result.map_err(|_| ParseError::BadInt(suffix.into()))
-            };
-
-            let parsed = match s.chars().nth(0) {
-                Some('U') => Segment::Up(parse_length()?),
-                Some('D') => Segment::Down(parse_length()?),
-                Some('L') => Segment::Left(parse_length()?),
-                Some('R') => Segment::Right(parse_length()?),
+            let direction = match s.chars().nth(0) {
+                Some('U') => Direction::North,
+                Some('D') => Direction::South,
+                Some('L') => Direction::West,
+                Some('R') => Direction::East,
                 Some(c) => return Err(ParseError::BadDirection(c)),
                 None => return Err(ParseError::EmptySegment),
             };
 
-            Ok(parsed)
+            let suffix = &s[1..];
+            let length = suffix
+                .parse::<u64>()
+                .map_err(|_| ParseError::BadInt(suffix.into()))?;
+
+            Ok(Segment { direction, length })
         }
     }
 
     #[derive(Debug)]
     pub struct Wire {
-        // Sequence of points the wire passes through. Does not include
-        // starting at the origin.
-        points: Vec<Point>,
+        // Map from each point the wire passes through to the number of steps
+        // it takes to first get there. Does not include the origin.
+        delays: HashMap<Coord, usize>,
     }
 
     impl Wire {
         fn from_segments(segments: Vec<Segment>) -> Wire {
-            let mut points: Vec<Point> = Vec::new();
-            let mut pos = Point::new(0, 0);
+            let mut delays = HashMap::new();
+            let mut pos: Coord = (0, 0);
+            let mut steps = 0;
 
             for segment in segments {
-                let mut new_points = pos.points_along_segment(segment);
-                if new_points.len() > 0 {
-                    pos = *new_points.last().unwrap();
-                    points.append(&mut new_points);
+                for _ in 0..segment.length {
+                    pos = pos + segment.direction;
+                    steps += 1;
+                    delays.entry(pos).or_insert(steps);
                 }
             }
 
-            Wire { points }
+            Wire { delays }
         }
 
-        pub fn intersect(&self, other: &Wire) -> HashSet<Point> {
-            let left: HashSet<&Point> = self.points.iter().collect();
-            let right: HashSet<&Point> = other.points.iter().collect();
-
-            left.intersection(&right).map(|p| *p.clone()).collect()
+        pub fn intersect(&self, other: &Wire) -> HashSet<Coord> {
+            self.delays
+                .keys()
+                .filter(|p| other.delays.contains_key(p))
+                .cloned()
+                .collect()
         }
 
-        pub fn delay_for(&self, point: &Point) -> Option<usize> {
-            // Add 1 because the point at index 0 has a signal delay of 1 (we
-            // don't store the starting point of the origin in self.points).
-            self.points.iter().position(|p| p == point).map(|x| x + 1)
+        pub fn delay_for(&self, point: &Coord) -> Option<usize> {
+            self.delays.get(point).copied()
         }
     }
 
@@ -206,65 +205,13 @@ mod wire {
         }
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-    pub struct Point {
-        x: i64,
-        y: i64,
-    }
-
-    impl Point {
-        pub fn new(x: i64, y: i64) -> Point {
-            Self { x, y }
-        }
-
-        pub fn manhattan_distance_from_origin(&self) -> u64 {
-            (self.x.abs() + self.y.abs()) as u64
-        }
-
-        fn points_along_segment(&self, segment: Segment) -> Vec<Point> {
-            let mut out = vec![];
-
-            match segment {
-                Segment::Up(n) => {
-                    for i in 1..n + 1 {
-                        out.push(Point {
-                            x: self.x,
-                            y: self.y + i as i64,
-                        });
-                    }
-                }
-                Segment::Down(n) => {
-                    for i in 1..n + 1 {
-                        out.push(Point {
-                            x: self.x,
-                            y: self.y - i as i64,
-                        });
-                    }
-                }
-                Segment::Left(n) => {
-                    for i in 1..n + 1 {
-                        out.push(Point {
-                            x: self.x - i as i64,
-                            y: self.y,
-                        });
-                    }
-                }
-                Segment::Right(n) => {
-                    for i in 1..n + 1 {
-                        out.push(Point {
-                            x: self.x + i as i64,
-                            y: self.y,
-                        });
-                    }
-                }
-            }
-
-            out
-        }
+    pub fn manhattan_distance_from_origin(point: &Coord) -> u64 {
+        (point.0.abs() + point.1.abs()) as u64
     }
 }
 
-use wire::{ParseError, Point, Wire};
+use crate::grid::Coord;
+use wire::{manhattan_distance_from_origin, ParseError, Wire};
 
 struct WirePair {
     first: Wire,
@@ -278,52 +225,127 @@ impl str::FromStr for WirePair {
         let parsed: Result<Vec<Wire>, ParseError> =
             s.lines().map(|line| line.parse::<Wire>()).collect();
 
-        let mut wires = parsed?;
+        WirePair::from_wires(parsed?)
+    }
+}
 
+impl WirePair {
+    fn from_wires(mut wires: Vec<Wire>) -> Result<WirePair, ParseError> {
         match wires.len() {
             2 => Ok(WirePair {
                 first: wires.pop().unwrap(),
                 second: wires.pop().unwrap(),
             }),
-            n => Err(ParseError::WrongNumberOfWires(n).into()),
+            n => Err(ParseError::WrongNumberOfWires(n)),
         }
     }
 }
 
-pub fn run() -> utils::ProblemResult<()> {
-    let WirePair { first, second } = WirePair::for_problem(3)?;
+/// The intersection closest to the origin, and its distance from the origin.
+fn closest_intersection(first: &Wire, second: &Wire) -> Option<(Coord, u64)> {
+    first
+        .intersect(second)
+        .into_iter()
+        .map(|p| (p, manhattan_distance_from_origin(&p)))
+        .min_by_key(|&(_, distance)| distance)
+}
 
-    let intersection = first.intersect(&second);
+/// The intersection the signal reaches soonest along both wires, and the
+/// combined delay to reach it.
+fn least_delay_intersection(first: &Wire, second: &Wire) -> Option<(Coord, usize)> {
+    first
+        .intersect(second)
+        .into_iter()
+        .map(|p| {
+            // Unwraps are safe here b/c we know these points are in the trace
+            // of both wires.
+            let delay = first.delay_for(&p).unwrap() + second.delay_for(&p).unwrap();
+            (p, delay)
+        })
+        .min_by_key(|&(_, delay)| delay)
+}
 
-    println!("\nPart 1");
-    println!("------");
-    let closest_to_origin: &Point = intersection
-        .iter()
-        .min_by_key(|p| p.manhattan_distance_from_origin())
-        .expect("Lines do not intersect!");
+pub fn run(part: Part) -> utils::ProblemResult<()> {
+    let WirePair { first, second } = WirePair::from_wires(utils::lines_for_problem(3)?)?;
 
-    println!("Closest point to origin is {:?}.", closest_to_origin);
-    println!(
-        "Distance is {}.",
-        closest_to_origin.manhattan_distance_from_origin()
-    );
+    if part.includes(Part::One) {
+        println!("\nPart 1");
+        println!("------");
+        let (closest_to_origin, distance) =
+            closest_intersection(&first, &second).expect("Lines do not intersect!");
+
+        println!("Closest point to origin is {:?}.", closest_to_origin);
+        println!("Distance is {}.", distance);
+    }
+
+    if !part.includes(Part::Two) {
+        return Ok(());
+    }
 
     println!("\nPart 2");
     println!("------");
 
-    let total_delay = |p: &&Point| -> usize {
-        // Unwraps are safe here b/c we know these points are in
-        // the trace of both wires.
-        first.delay_for(p).unwrap() + second.delay_for(p).unwrap()
-    };
-
-    let least_delay: &Point = intersection
-        .iter()
-        .min_by_key(total_delay)
-        .expect("Lines do not intersect!");
+    let (least_delay, delay) =
+        least_delay_intersection(&first, &second).expect("Lines do not intersect!");
 
     println!("Shortest delay point is {:?}", least_delay);
-    println!("Delay is {}", total_delay(&least_delay));
+    println!("Delay is {}", delay);
 
     Ok(())
 }
+
+mod tests {
+    #[allow(dead_code)]
+    const EXAMPLE1: &str = "R75,D30,R83,U83,L12,D49,R71,U7,L72
+U62,R66,U55,R34,D71,R55,D58,R83";
+
+    #[allow(dead_code)]
+    const EXAMPLE2: &str = "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
+U98,R91,D20,R16,D67,R40,U7,R15,U6,R7";
+
+    #[test]
+    fn test_closest_intersection() {
+        use super::{closest_intersection, WirePair};
+
+        for &(input, expected) in [(EXAMPLE1, 159), (EXAMPLE2, 135)].iter() {
+            let WirePair { first, second } = input.parse().unwrap();
+            let (_, distance) = closest_intersection(&first, &second).unwrap();
+            assert_eq!(distance, expected);
+        }
+    }
+
+    #[test]
+    fn test_small_example() {
+        use super::{closest_intersection, least_delay_intersection, WirePair};
+
+        let WirePair { first, second } = "R8,U5,L5,D3\nU7,R6,D4,L4".parse().unwrap();
+        assert_eq!(closest_intersection(&first, &second), Some(((3, -3), 6)));
+        assert_eq!(
+            least_delay_intersection(&first, &second),
+            Some(((6, -5), 30))
+        );
+    }
+
+    #[test]
+    fn test_least_delay_intersection() {
+        use super::{least_delay_intersection, WirePair};
+
+        for &(input, expected) in [(EXAMPLE1, 610), (EXAMPLE2, 410)].iter() {
+            let WirePair { first, second } = input.parse().unwrap();
+            let (_, delay) = least_delay_intersection(&first, &second).unwrap();
+            assert_eq!(delay, expected);
+        }
+    }
+
+    #[test]
+    fn test_delay_uses_first_visit() {
+        use super::wire::Wire;
+
+        // The wire passes through (1, 0) after 1 step and again after 5. Up is
+        // north, which is toward negative y.
+        let wire: Wire = "R2,U1,L1,D2".parse().unwrap();
+        assert_eq!(wire.delay_for(&(1, 0)), Some(1));
+        assert_eq!(wire.delay_for(&(1, 1)), Some(6));
+        assert_eq!(wire.delay_for(&(0, 0)), None);
+    }
+}
@@ -32,53 +32,129 @@
 /// 111122 meets the criteria (even though 1 is repeated more than twice, it still contains a double 22).
 
 /// How many different passwords within the range given in your puzzle input meet all of the criteria?
+use std::ops::RangeInclusive;
+
 use crate::utils;
+use crate::utils::Part;
 
-pub fn run() -> utils::ProblemResult<()> {
-    let mut count1 = 0;
-    let mut count2 = 0;
-    let mut buf: [u8; 6] = [0; 6];
+pub fn run(part: Part) -> utils::ProblemResult<()> {
+    if part.includes(Part::One) {
+        let count1 = count_valid(264360..=746325, false);
+        println!("Num Valid Passwords (Part 1): {}", count1);
+    }
 
-    for i in 264360..=746325 {
-        buf.copy_from_slice(&i.to_string().as_bytes()[..6]);
+    if part.includes(Part::Two) {
+        let count2 = count_valid(264360..=746325, true);
+        println!("Num Valid Passwords (Part 2): {}", count2);
+    }
 
-        if is_valid_password1(&buf) {
-            count1 += 1;
-        }
+    Ok(())
+}
 
-        if is_valid_password2(&buf) {
-            count2 += 1;
-        }
-    }
+/// Which set of password criteria to check.
+#[derive(Debug, Clone, Copy)]
+pub enum Rule {
+    /// Some pair of adjacent digits is equal.
+    Part1,
+    /// Some pair of adjacent digits is equal and not part of a larger group.
+    Part2,
+}
 
-    println!("Num Valid Passwords (Part 1): {}", count1);
-    println!("Num Valid Passwords (Part 2): {}", count2);
+/// Count the numbers in `range` that are valid passwords, using the part 2
+/// criteria if `part2` is true.
+fn count_valid(range: RangeInclusive<u32>, part2: bool) -> usize {
+    let rule = if part2 { Rule::Part2 } else { Rule::Part1 };
+    valid_passwords(*range.start(), *range.end(), rule).count()
+}
 
-    Ok(())
+/// Iterate over the numbers in `lo..=hi` that are valid passwords under
+/// `rule`.
+fn valid_passwords(lo: u32, hi: u32, rule: Rule) -> impl Iterator<Item = u32> {
+    (lo..=hi).filter(move |&i| {
+        let pw = digits(i);
+        match rule {
+            Rule::Part1 => is_valid_password1(&pw),
+            Rule::Part2 => is_valid_password2(&pw),
+        }
+    })
 }
 
-fn is_valid_password1(pw: &[u8; 6]) -> bool {
-    return digits_are_monotonic(pw) && contains_repeat(pw);
+fn digits(i: u32) -> Vec<u8> {
+    i.to_string().bytes().map(|b| b - b'0').collect()
 }
 
-fn is_valid_password2(pw: &[u8; 6]) -> bool {
-    return digits_are_monotonic(pw) && contains_exact_repeat(pw);
+fn is_valid_password1(pw: &[u8]) -> bool {
+    digits_are_monotonic(pw) && contains_repeat(pw)
 }
 
-fn digits_are_monotonic(pw: &[u8; 6]) -> bool {
+fn is_valid_password2(pw: &[u8]) -> bool {
+    digits_are_monotonic(pw) && contains_exact_repeat(pw)
+}
+
+fn digits_are_monotonic(pw: &[u8]) -> bool {
     pw.windows(2).all(|x| x[0] <= x[1])
 }
 
-fn contains_repeat(pw: &[u8; 6]) -> bool {
+fn contains_repeat(pw: &[u8]) -> bool {
     pw.windows(2).any(|x| x[0] == x[1])
 }
 
-fn contains_exact_repeat(pw: &[u8; 6]) -> bool {
-    let at_start = (pw[0] == pw[1]) && (pw[1] != pw[2]);
-    let at_end = (pw[4] == pw[5]) && (pw[3] != pw[4]);
-    let internal = pw
-        .windows(4)
-        .any(|x| (x[0] != x[1]) && (x[1] == x[2]) && (x[2] != x[3]));
+/// Check whether `pw` contains a run of exactly two equal digits.
+fn contains_exact_repeat(pw: &[u8]) -> bool {
+    let mut i = 0;
+    while i < pw.len() {
+        let run = pw[i..].iter().take_while(|&&d| d == pw[i]).count();
+        if run == 2 {
+            return true;
+        }
+        i += run;
+    }
+    false
+}
+
+mod tests {
+    #[test]
+    fn test_valid_passwords() {
+        use super::{valid_passwords, Rule};
+
+        let part1: Vec<u32> = valid_passwords(111122, 111122, Rule::Part1).collect();
+        assert_eq!(part1, vec![111122]);
+        let part2: Vec<u32> = valid_passwords(111122, 111122, Rule::Part2).collect();
+        assert_eq!(part2, vec![111122]);
+
+        assert_eq!(valid_passwords(123444, 123444, Rule::Part1).count(), 1);
+        assert_eq!(valid_passwords(123444, 123444, Rule::Part2).count(), 0);
+
+        let near: Vec<u32> = valid_passwords(111109, 111125, Rule::Part2).collect();
+        assert_eq!(near, vec![111122]);
+    }
+
+    #[test]
+    fn test_spec_examples() {
+        use super::count_valid;
 
-    at_start || at_end || internal
+        let valid = |n: u32, part2: bool| count_valid(n..=n, part2) == 1;
+
+        assert!(valid(111111, false));
+        assert!(!valid(223450, false));
+        assert!(!valid(123789, false));
+
+        assert!(valid(112233, true));
+        assert!(!valid(123444, true));
+        assert!(valid(111122, true));
+    }
+
+    #[test]
+    fn test_other_lengths() {
+        use super::{count_valid, is_valid_password2};
+
+        // 11, 22, ..., 99.
+        assert_eq!(count_valid(10..=99, false), 9);
+        assert_eq!(count_valid(10..=99, true), 9);
+        // 111 has no exact pair.
+        assert_eq!(count_valid(111..=111, true), 0);
+
+        assert!(is_valid_password2(&[1, 1, 2, 2, 2, 2, 2, 2]));
+        assert!(!is_valid_password2(&[1, 1, 1, 2, 2, 2, 3, 4]));
+    }
 }
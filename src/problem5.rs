@@ -167,26 +167,31 @@
 /// What is the diagnostic code for system ID 5?
 use crate::intcode::{Program, StaticIO};
 use crate::utils;
-use crate::utils::ProblemInput;
+use crate::utils::{Part, ProblemInput};
 
-pub fn run() -> utils::ProblemResult<()> {
+pub fn run(part: Part) -> utils::ProblemResult<()> {
     let program = Program::for_problem(5)?;
 
-    // Part 1
-    println!("Part 1");
-    println!("------");
-    let mut io = StaticIO::new(vec![1]);
-
-    match program.run(&mut io) {
-        Ok(_) => {
-            let outputs = io.outputs();
-            println!("{:?}", outputs);
-        }
-        Err(e) => {
-            println!("Error executing program:\n{}", e);
+    if part.includes(Part::One) {
+        println!("Part 1");
+        println!("------");
+        let mut io = StaticIO::new(vec![1]);
+
+        match program.run(&mut io) {
+            Ok(_) => {
+                let outputs = io.outputs();
+                println!("{:?}", outputs);
+            }
+            Err(e) => {
+                println!("Error executing program:\n{}", e);
+            }
         }
     }
 
+    if !part.includes(Part::Two) {
+        return Ok(());
+    }
+
     // Part 2
     println!("Part 2");
     println!("------");
@@ -132,6 +132,7 @@ use std::str::FromStr;
 
 use crate::tree::Tree;
 use crate::utils;
+use crate::utils::Part;
 
 #[derive(Debug)]
 struct Orbits {
@@ -169,9 +170,28 @@ impl Orbits {
     }
 
     /// Find distances of all nodes from the root node.
+    #[allow(dead_code)]
     pub fn distances_from_root(&self) -> HashMap<String, u64> {
         find_depths(&self.root, &self.children)
     }
+
+    /// Compute the total number of direct and indirect orbits.
+    ///
+    /// This is equivalent to summing the values of `distances_from_root`, but
+    /// doesn't allocate a map entry for every node.
+    pub fn total_orbit_count(&self) -> u64 {
+        let mut total = 0;
+        let mut stack: Vec<(&str, u64)> = vec![(&self.root, 0)];
+
+        while let Some((node, depth)) = stack.pop() {
+            total += depth;
+            if let Some(children) = self.children.get(node) {
+                stack.extend(children.iter().map(|c| (&c[..], depth + 1)));
+            }
+        }
+
+        total
+    }
 }
 
 impl<'a> Tree<&'a str> for &'a Orbits {
@@ -249,35 +269,70 @@ fn read_orbits(path: &Path) -> utils::ProblemResult<Orbits> {
     file_content.parse::<Orbits>().map_err(|e| e.into())
 }
 
-pub fn run() -> utils::ProblemResult<()> {
+pub fn run(part: Part) -> utils::ProblemResult<()> {
     let here = Path::new(file!()).parent().unwrap();
     let input_path = here.join("inputs/problem6_input.txt");
 
     match read_orbits(&input_path) {
         Ok(ref orbits) => {
-            println!("Part 1");
-            println!("------");
-            let total: u64 = orbits.distances_from_root().values().sum();
-            println!("Total Number of Orbits: {:?}", total);
-
-            println!("Part 2");
-            println!("------");
-
-            // Find shortest path from YOU to the planet santa is orbiting.
-            let target = orbits.parent("SAN").expect("Santa has no parent!");
-            let path = orbits.shortest_path("YOU", target);
-            println!("Shortest Path from YOU to parent(SAN): {:?}", path);
-
-            // The number of transitions is the number of nodes in the path,
-            // minus 2. To see this, consider that if we're already orbiting
-            // the same planet as santa, the path from YOU to parent(SAN) has
-            // length 2 ([YOU, parent(SAN)], and there are no hops to
-            // perform. For each node between YOU and parent(SAN), we add one
-            // node to the path, and one hop.
-            println!("Number of Orbital Transitions: {}", path.len() - 2);
+            if part.includes(Part::One) {
+                println!("Part 1");
+                println!("------");
+                let total = orbits.total_orbit_count();
+                println!("Total Number of Orbits: {:?}", total);
+            }
+
+            if part.includes(Part::Two) {
+                println!("Part 2");
+                println!("------");
+
+                // Find shortest path from YOU to the planet santa is orbiting.
+                let target = orbits.parent("SAN").expect("Santa has no parent!");
+                let path = orbits.shortest_path("YOU", target);
+                println!("Shortest Path from YOU to parent(SAN): {:?}", path);
+
+                // The number of transitions is the number of nodes in the
+                // path, minus 2. To see this, consider that if we're already
+                // orbiting the same planet as santa, the path from YOU to
+                // parent(SAN) has length 2 ([YOU, parent(SAN)], and there are
+                // no hops to perform. For each node between YOU and
+                // parent(SAN), we add one node to the path, and one hop.
+                println!("Number of Orbital Transitions: {}", path.len() - 2);
+            }
 
             Ok(())
         }
         Err(e) => utils::bail(format!("Error reading orbits:\n {}", e)),
     }
 }
+
+mod tests {
+    #[test]
+    fn test_total_orbit_count() {
+        use super::Orbits;
+
+        let orbits: Orbits = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L"
+            .parse()
+            .unwrap();
+
+        let total: u64 = orbits.distances_from_root().values().sum();
+        assert_eq!(total, 42);
+        assert_eq!(orbits.total_orbit_count(), 42);
+    }
+
+    #[test]
+    fn test_lowest_common_ancestor() {
+        use super::Orbits;
+        use crate::tree::Tree;
+
+        let orbits: Orbits = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L"
+            .parse()
+            .unwrap();
+        let tree = &orbits;
+
+        assert_eq!(tree.lowest_common_ancestor("K", "I"), Some("D"));
+        assert_eq!(tree.lowest_common_ancestor("L", "H"), Some("B"));
+        assert_eq!(tree.lowest_common_ancestor("F", "E"), Some("E"));
+        assert_eq!(tree.shortest_path("K", "I"), vec!["K", "J", "E", "D", "I"]);
+    }
+}
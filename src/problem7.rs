@@ -137,7 +137,7 @@
 use crate::intcode::{Program, IO};
 use crate::utils;
 use crate::utils::permute::permutations;
-use crate::utils::ProblemInput;
+use crate::utils::{Part, ProblemInput};
 
 mod amp {
     use std::cell::Cell;
@@ -204,17 +204,25 @@ impl<'a> IO for AmpIO<'a> {
     }
 }
 
-pub fn run() -> utils::ProblemResult<()> {
+pub fn run(part: Part) -> utils::ProblemResult<()> {
     let program = Program::for_problem(7)?;
 
-    println!("{:?}", find_max_thrusts(&program)?);
+    if part.includes(Part::One) {
+        println!("Max thrust (Part 1): {}", find_max_serial_thrust(&program)?);
+    }
+
+    if part.includes(Part::Two) {
+        println!(
+            "Max thrust (Part 2): {}",
+            find_max_feedback_thrust(&program)?
+        );
+    }
 
     Ok(())
 }
 
-fn find_max_thrusts(program: &Program) -> utils::ProblemResult<(i64, i64)> {
-    // Part 1
-    let mut best1 = 0;
+fn find_max_serial_thrust(program: &Program) -> utils::ProblemResult<i64> {
+    let mut best = 0;
     for permutation in permutations(0..5) {
         let amps: Vec<Amplifier> = permutation.into_iter().map(Amplifier::new).collect();
 
@@ -232,13 +240,16 @@ fn find_max_thrusts(program: &Program) -> utils::ProblemResult<(i64, i64)> {
 
         let result = amps[4].get_signal();
 
-        if result > best1 {
-            best1 = result;
+        if result > best {
+            best = result;
         }
     }
 
-    // Part 2
-    let mut best2 = 0;
+    Ok(best)
+}
+
+fn find_max_feedback_thrust(program: &Program) -> utils::ProblemResult<i64> {
+    let mut best = 0;
     for permutation in permutations(5..10) {
         let amps: Vec<Amplifier> = permutation.into_iter().map(Amplifier::new).collect();
 
@@ -250,14 +261,65 @@ fn find_max_thrusts(program: &Program) -> utils::ProblemResult<(i64, i64)> {
             ios.push(AmpIO::new(&pair[1], Some(&pair[0])));
         }
 
-        program.run_concurrently(&mut ios)?;
+        let (_, outputs) = program.run_concurrently_collecting(&mut ios)?;
 
-        let result = amps[4].get_signal();
+        // The final signal is the last value output by the last amp.
+        let result = match outputs[4].last() {
+            Some(&signal) => signal,
+            None => return utils::bail("Last amplifier produced no output"),
+        };
 
-        if result > best2 {
-            best2 = result;
+        if result > best {
+            best = result;
         }
     }
 
-    Ok((best1, best2))
+    Ok(best)
+}
+
+mod tests {
+    #[test]
+    fn test_max_serial_thrust() {
+        use super::find_max_serial_thrust;
+        use crate::intcode::Program;
+
+        let examples = vec![
+            ("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0", 43210),
+            (
+                "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+                54321,
+            ),
+            (
+                "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0",
+                65210,
+            ),
+        ];
+
+        for (code, expected) in examples {
+            let program: Program = code.parse().unwrap();
+            assert_eq!(find_max_serial_thrust(&program).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_max_feedback_thrust() {
+        use super::find_max_feedback_thrust;
+        use crate::intcode::Program;
+
+        let examples = vec![
+            (
+                "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+                139629729,
+            ),
+            (
+                "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+                18216,
+            ),
+        ];
+
+        for (code, expected) in examples {
+            let program: Program = code.parse().unwrap();
+            assert_eq!(find_max_feedback_thrust(&program).unwrap(), expected);
+        }
+    }
 }
@@ -81,7 +81,10 @@ What message is produced after decoding your image?
 
  */
 
-use crate::utils::{bail, read_problem_file, ProblemResult};
+use std::error::Error;
+use std::str::FromStr;
+
+use crate::utils::{bail, Part, ProblemInput, ProblemResult};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Pixel {
@@ -109,38 +112,24 @@ impl Pixel {
     }
 }
 
-struct Layer<'a> {
-    pixels: &'a [Pixel],
-    width: usize,
-    height: usize,
+struct Layer<'a, P> {
+    pixels: &'a [P],
 }
 
-impl Layer<'_> {
-    pub fn count(&self, byte: Pixel) -> usize {
-        self.pixels.iter().cloned().filter(|&b| b == byte).count()
-    }
-
-    pub fn at(&self, col: usize, row: usize) -> Pixel {
-        if col >= self.width {
-            panic!("Column {} is out of bounds for width {}", col, self.width);
-        }
-
-        if row >= self.height {
-            panic!("Row {} is out of bounds for width {}", row, self.height);
-        }
-
-        return self.pixels[row * self.width + col];
+impl<P: Copy + PartialEq> Layer<'_, P> {
+    pub fn count(&self, value: P) -> usize {
+        self.pixels.iter().cloned().filter(|&p| p == value).count()
     }
 }
 
-struct Layers<'a> {
+struct Layers<'a, P> {
     ix: usize,
     max_ix: usize,
-    image: &'a Image,
+    image: &'a Image<P>,
 }
 
-impl<'a> Iterator for Layers<'a> {
-    type Item = Layer<'a>;
+impl<'a, P> Iterator for Layers<'a, P> {
+    type Item = Layer<'a, P>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.ix == self.max_ix {
@@ -153,55 +142,37 @@ impl<'a> Iterator for Layers<'a> {
     }
 }
 
-struct Image {
-    pixels: Vec<Pixel>,
+/// A layered image. Pixels are usually `Pixel`s, but any kind of value can
+/// be split into layers.
+struct Image<P = Pixel> {
+    pixels: Vec<P>,
     width: usize,
     height: usize,
 }
 
-impl Image {
-    fn new(pixels: Vec<Pixel>, width: usize, height: usize) -> Image {
+impl<P> Image<P> {
+    fn new(pixels: Vec<P>, width: usize, height: usize) -> ProblemResult<Image<P>> {
         let total_pixels = pixels.len();
 
+        if width == 0 || height == 0 {
+            return bail(format!("Invalid dimensions ({}, {})", width, height));
+        }
+
         if total_pixels % (width * height) != 0 {
-            panic!(
+            return bail(format!(
                 "Pixel count ({}) doesn't match dimensions ({}, {})",
-                pixels.len(),
-                width,
-                height,
-            );
+                total_pixels, width, height,
+            ));
         }
 
-        Image {
+        Ok(Image {
             pixels,
             width,
             height,
-        }
+        })
     }
 
-    pub fn render(&self) -> String {
-        let mut out = String::new();
-
-        for j in 0..self.height {
-            for i in 0..self.width {
-                out.push(self.effective_pixel(i, j).to_char());
-            }
-            out.push('\n');
-        }
-        out
-    }
-
-    pub fn effective_pixel(&self, i: usize, j: usize) -> Pixel {
-        for layer in self.layers() {
-            let pixel = layer.at(i, j);
-            if pixel != Pixel::Transparent {
-                return pixel;
-            }
-        }
-        return Pixel::Transparent;
-    }
-
-    pub fn layers(&self) -> impl Iterator<Item = Layer> {
+    pub fn layers(&self) -> Layers<'_, P> {
         Layers {
             ix: 0,
             max_ix: self.num_layers(),
@@ -209,20 +180,16 @@ impl Image {
         }
     }
 
-    pub fn layer(&self, i: usize) -> Layer {
+    pub fn layer(&self, i: usize) -> Layer<'_, P> {
         let nlayers = self.num_layers();
-        let pixels_per_layer = self.pixels.len() / nlayers;
+        let pixels_per_layer = self.layer_size();
 
         if i >= nlayers {
             panic!("Index out of bounds: {} >= {}", i, nlayers);
         }
         let pixels = &self.pixels[i * pixels_per_layer..(i + 1) * pixels_per_layer];
 
-        Layer {
-            pixels: pixels,
-            width: self.width,
-            height: self.height,
-        }
+        Layer { pixels }
     }
 
     fn num_layers(&self) -> usize {
@@ -230,31 +197,139 @@ impl Image {
     }
 
     fn layer_size(&self) -> usize {
-        (self.width * self.height)
+        self.width * self.height
     }
 }
 
-fn read_input(width: usize, height: usize) -> ProblemResult<Image> {
-    let file_content: String = read_problem_file(8)?;
-    let pixels: ProblemResult<Vec<Pixel>> =
-        file_content.trim().bytes().map(Pixel::from_byte).collect();
+impl Image<Pixel> {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        for row in self.composite().chunks(self.width) {
+            out.extend(row.iter().map(Pixel::to_char));
+            out.push('\n');
+        }
+        out
+    }
 
-    Ok(Image::new(pixels?, width, height))
+    /// Stack the layers into the image that's actually displayed.
+    ///
+    /// Each pixel is the frontmost non-transparent pixel at its position.
+    /// Pixels are filled in one layer at a time, stopping once every pixel
+    /// has been filled.
+    pub fn composite(&self) -> Vec<Pixel> {
+        let mut out = vec![Pixel::Transparent; self.layer_size()];
+        let mut remaining = out.len();
+
+        for layer in self.layers() {
+            if remaining == 0 {
+                break;
+            }
+            for (dest, &pixel) in out.iter_mut().zip(layer.pixels.iter()) {
+                if *dest == Pixel::Transparent && pixel != Pixel::Transparent {
+                    *dest = pixel;
+                    remaining -= 1;
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// The digits of an image, in order, before they're split into layers.
+struct ImageData(Vec<Pixel>);
+
+impl FromStr for ImageData {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pixels: ProblemResult<Vec<Pixel>> = s.trim().bytes().map(Pixel::from_byte).collect();
+        Ok(ImageData(pixels?))
+    }
 }
 
-pub fn run() -> ProblemResult<()> {
-    let image = read_input(25, 6)?;
-    let most_zeros = image
-        .layers()
-        .min_by_key(|layer| layer.count(Pixel::Black))
-        .unwrap();
+fn read_input(width: usize, height: usize) -> ProblemResult<Image> {
+    let ImageData(pixels) = ImageData::for_problem(8)?;
+    Image::new(pixels, width, height)
+}
+
+pub fn run(part: Part) -> ProblemResult<()> {
+    const WIDTH: usize = 25;
+    const HEIGHT: usize = 6;
 
-    println!(
-        "{}",
-        most_zeros.count(Pixel::White) * most_zeros.count(Pixel::Transparent)
-    );
+    let image = read_input(WIDTH, HEIGHT)?;
 
-    println!("{}", image.render());
+    if part.includes(Part::One) {
+        let most_zeros = image
+            .layers()
+            .min_by_key(|layer| layer.count(Pixel::Black))
+            .unwrap();
+
+        println!(
+            "{}",
+            most_zeros.count(Pixel::White) * most_zeros.count(Pixel::Transparent)
+        );
+    }
+
+    if part.includes(Part::Two) {
+        println!("{}", image.render());
+    }
 
     Ok(())
 }
+
+mod tests {
+    #[test]
+    fn test_composite() {
+        use super::{Image, Pixel};
+
+        let pixels = "0222112222120000"
+            .bytes()
+            .map(|b| Pixel::from_byte(b).unwrap())
+            .collect();
+        let image = Image::new(pixels, 2, 2).unwrap();
+
+        assert_eq!(
+            image.composite(),
+            vec![Pixel::Black, Pixel::White, Pixel::White, Pixel::Black]
+        );
+        assert_eq!(image.render(), "\u{2588}\u{2591}\n\u{2591}\u{2588}\n");
+    }
+
+    #[allow(dead_code)]
+    fn digits(s: &str) -> Vec<u8> {
+        s.bytes().map(|b| b - b'0').collect()
+    }
+
+    #[test]
+    fn test_layers() {
+        use super::Image;
+
+        let image = Image::new(digits("123456789012"), 3, 2).unwrap();
+        let layers: Vec<&[u8]> = image.layers().map(|layer| layer.pixels).collect();
+        assert_eq!(
+            layers,
+            vec![&[1, 2, 3, 4, 5, 6][..], &[7, 8, 9, 0, 1, 2][..]]
+        );
+
+        assert_eq!(image.layer(1).count(2), 1);
+    }
+
+    #[test]
+    fn test_bad_dimensions() {
+        use super::Image;
+
+        assert!(Image::new(digits("1234567"), 3, 2).is_err());
+        assert!(Image::new(digits("123456"), 0, 2).is_err());
+    }
+
+    #[test]
+    fn test_parse_image_data() {
+        use super::{ImageData, Pixel};
+
+        let ImageData(pixels) = "012\n".parse().unwrap();
+        assert_eq!(pixels, vec![Pixel::Black, Pixel::White, Pixel::Transparent]);
+        assert!("0123".parse::<ImageData>().is_err());
+    }
+}
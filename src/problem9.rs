@@ -77,30 +77,24 @@ distress signal?
 
 */
 
-use crate::intcode::{Program, StaticIO};
+use crate::intcode::Program;
 use crate::utils;
-use crate::utils::ProblemInput;
+use crate::utils::{Part, ProblemInput};
 
-pub fn run() -> utils::ProblemResult<()> {
+pub fn run(part: Part) -> utils::ProblemResult<()> {
     let program = Program::for_problem(9)?;
 
-    // Part 1
-    println!("Part 1");
-    println!("------");
-    let mut io = StaticIO::new(vec![1]);
-
-    program.run(&mut io)?;
-
-    println!("{:?}", io.outputs());
-
-    // Part 1
-    println!("Part 2");
-    println!("------");
-    let mut io = StaticIO::new(vec![2]);
-
-    program.run(&mut io)?;
-
-    println!("{:?}", io.outputs());
+    if part.includes(Part::One) {
+        println!("Part 1");
+        println!("------");
+        println!("{:?}", program.run_io(vec![1])?);
+    }
+
+    if part.includes(Part::Two) {
+        println!("Part 2");
+        println!("------");
+        println!("{:?}", program.run_io(vec![2])?);
+    }
 
 
     Ok(())
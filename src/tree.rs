@@ -8,10 +8,16 @@ pub trait Tree<T: Clone + Copy + Eq + Hash> {
     fn parent(&self, node: T) -> Option<T>;
 
     /// Get all ancestors of `node`, up to the root of the tree.
+    ///
+    /// If the parent relation contains a cycle, stop before revisiting a node.
     fn ancestors(&self, mut node: T) -> Vec<T> {
         let mut out: Vec<T> = vec![node];
+        let mut seen: HashSet<T> = out.iter().cloned().collect();
 
         while let Some(parent) = self.parent(node) {
+            if !seen.insert(parent) {
+                break;
+            }
             out.push(parent);
             node = parent;
         }
@@ -19,6 +25,39 @@ pub trait Tree<T: Clone + Copy + Eq + Hash> {
         out
     }
 
+    /// Check whether following parents from `node` leads into a cycle.
+    ///
+    /// If so, return the nodes of the cycle in parent order, starting from the
+    /// first node of the cycle reached from `node`.
+    #[allow(dead_code)]
+    fn detect_cycle(&self, node: T) -> Option<Vec<T>> {
+        let mut path: Vec<T> = vec![node];
+        let mut seen: HashSet<T> = path.iter().cloned().collect();
+        let mut current = node;
+
+        while let Some(parent) = self.parent(current) {
+            if !seen.insert(parent) {
+                let start = path.iter().position(|&x| x == parent).unwrap();
+                return Some(path.split_off(start));
+            }
+            path.push(parent);
+            current = parent;
+        }
+
+        None
+    }
+
+    /// Find the deepest node that is an ancestor of both `a` and `b`. A node
+    /// counts as its own ancestor. Returns None if `a` and `b` are in
+    /// different trees.
+    #[allow(dead_code)]
+    fn lowest_common_ancestor(&self, a: T, b: T) -> Option<T> {
+        let a_ancestors: HashSet<T> = self.ancestors(a).into_iter().collect();
+        self.ancestors(b)
+            .into_iter()
+            .find(|x| a_ancestors.contains(x))
+    }
+
     /// Find the shortest path from `start` to `end`, inclusive of both endpoints.
     fn shortest_path(&self, start: T, end: T) -> Vec<T> {
         let mut start_to_root = self.ancestors(start);
@@ -50,3 +89,55 @@ pub trait Tree<T: Clone + Copy + Eq + Hash> {
         out
     }
 }
+
+mod tests {
+    #[allow(dead_code)]
+    struct ParentMap(std::collections::HashMap<char, char>);
+
+    impl super::Tree<char> for ParentMap {
+        fn parent(&self, node: char) -> Option<char> {
+            self.0.get(&node).copied()
+        }
+    }
+
+    #[allow(dead_code)]
+    fn parent_map(pairs: &[(char, char)]) -> ParentMap {
+        ParentMap(pairs.iter().cloned().collect())
+    }
+
+    #[test]
+    fn test_detect_cycle() {
+        use super::Tree;
+
+        // a -> b -> c -> d -> b
+        let tree = parent_map(&[('a', 'b'), ('b', 'c'), ('c', 'd'), ('d', 'b')]);
+        assert_eq!(tree.detect_cycle('a'), Some(vec!['b', 'c', 'd']));
+        assert_eq!(tree.detect_cycle('c'), Some(vec!['c', 'd', 'b']));
+        assert_eq!(tree.ancestors('a'), vec!['a', 'b', 'c', 'd']);
+
+        // A node that's its own parent.
+        let tree = parent_map(&[('a', 'a')]);
+        assert_eq!(tree.detect_cycle('a'), Some(vec!['a']));
+        assert_eq!(tree.ancestors('a'), vec!['a']);
+
+        let tree = parent_map(&[('a', 'b'), ('b', 'c')]);
+        assert_eq!(tree.detect_cycle('a'), None);
+        assert_eq!(tree.ancestors('a'), vec!['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_lowest_common_ancestor() {
+        use super::Tree;
+
+        //   r     x
+        //  / \    |
+        // a   b   y
+        //     |
+        //     c
+        let tree = parent_map(&[('a', 'r'), ('b', 'r'), ('c', 'b'), ('y', 'x')]);
+        assert_eq!(tree.lowest_common_ancestor('a', 'c'), Some('r'));
+        assert_eq!(tree.lowest_common_ancestor('c', 'b'), Some('b'));
+        assert_eq!(tree.lowest_common_ancestor('c', 'c'), Some('c'));
+        assert_eq!(tree.lowest_common_ancestor('a', 'y'), None);
+    }
+}
@@ -1,7 +1,10 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fs;
+use std::hash::Hash;
+use std::iter::FromIterator;
 use std::path::Path;
 use std::str::FromStr;
 
@@ -31,6 +34,60 @@ pub fn bail<T, S: Into<String>>(msg: S) -> ProblemResult<T> {
     Err(Box::new(SimpleError(msg.into())))
 }
 
+/// Which part(s) of a problem to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    One,
+    Two,
+    Both,
+}
+
+impl Part {
+    /// Should `part` be run? `part` should be `Part::One` or `Part::Two`.
+    pub fn includes(self, part: Part) -> bool {
+        self == Part::Both || self == part
+    }
+}
+
+impl FromStr for Part {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            _ => Err(SimpleError(format!(
+                "Unknown part: {}. Expected 1 or 2.",
+                s
+            ))),
+        }
+    }
+}
+
+/// Answers computed by a problem, for the caller to print or check.
+///
+/// Parts that weren't run have no answer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProblemAnswers {
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+}
+
+impl fmt::Display for ProblemAnswers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = [&self.part1, &self.part2]
+            .iter()
+            .enumerate()
+            .filter_map(|(i, answer)| {
+                answer
+                    .as_ref()
+                    .map(|answer| format!("Part {}: {}", i + 1, answer))
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
 pub trait ProblemInput
 where
     Self: Sized,
@@ -57,6 +114,38 @@ where
     }
 }
 
+/// Read the input file for problem `n` and parse each line as a `T`.
+pub fn lines_for_problem<T: FromStr>(n: u64) -> ProblemResult<Vec<T>>
+where
+    <T as FromStr>::Err: fmt::Display,
+{
+    parse_lines(&read_problem_file(n)?)
+}
+
+/// Parse each line of `s` as a `T`.
+///
+/// If any lines fail to parse, the error lists each of them with its
+/// (1-based) line number.
+pub fn parse_lines<T: FromStr>(s: &str) -> ProblemResult<Vec<T>>
+where
+    <T as FromStr>::Err: fmt::Display,
+{
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        match line.parse::<T>() {
+            Ok(value) => parsed.push(value),
+            Err(e) => errors.push(format!("line {}: {} ({:?})", i + 1, e, line)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        bail(errors.join("\n"))
+    }
+}
+
 pub mod permute {
     pub struct Permutations<T: Clone> {
         values: Vec<T>,
@@ -79,7 +168,7 @@ pub mod permute {
         type Item = Vec<T>;
 
         fn next(&mut self) -> Option<Self::Item> {
-            if self.ix == self.max_ix {
+            if self.ix >= self.max_ix {
                 None
             } else {
                 let indices = get_permutation(self.ix, self.values.len());
@@ -95,6 +184,75 @@ pub mod permute {
         Permutations::new(values.collect())
     }
 
+    /// Permutations generated with Heap's algorithm.
+    ///
+    /// Each permutation differs from the previous one by a single swap, so
+    /// producing the next one is O(1) amortized instead of rebuilding it from
+    /// its index. Permutations come out in a different order than
+    /// `Permutations`.
+    pub struct HeapPermutations<T: Clone> {
+        values: Vec<T>,
+        // Heap's algorithm's per-level loop counters.
+        counters: Vec<usize>,
+        level: usize,
+        started: bool,
+    }
+
+    #[allow(dead_code)]
+    impl<T: Clone> HeapPermutations<T> {
+        fn new(values: Vec<T>) -> HeapPermutations<T> {
+            let counters = vec![0; values.len()];
+            HeapPermutations {
+                values,
+                counters,
+                level: 1,
+                started: false,
+            }
+        }
+
+        /// Advance to the next permutation in place and return a reference to
+        /// it, without cloning.
+        pub fn next_ref(&mut self) -> Option<&[T]> {
+            if !self.started {
+                self.started = true;
+                return Some(&self.values);
+            }
+
+            let n = self.values.len();
+            while self.level < n {
+                let i = self.level;
+                if self.counters[i] < i {
+                    if i.is_multiple_of(2) {
+                        self.values.swap(0, i);
+                    } else {
+                        self.values.swap(self.counters[i], i);
+                    }
+                    self.counters[i] += 1;
+                    self.level = 1;
+                    return Some(&self.values);
+                } else {
+                    self.counters[i] = 0;
+                    self.level += 1;
+                }
+            }
+
+            None
+        }
+    }
+
+    impl<T: Clone> Iterator for HeapPermutations<T> {
+        type Item = Vec<T>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_ref().map(|p| p.to_vec())
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn heap_permutations<T: Clone, C: Iterator<Item = T>>(values: C) -> HeapPermutations<T> {
+        HeapPermutations::new(values.collect())
+    }
+
     fn get_permutation(mut i: usize, size: usize) -> Vec<usize> {
         let mut modulus: usize = (1..=size).product();
         let mut out: Vec<usize> = (0..size).collect();
@@ -142,6 +300,279 @@ pub mod permute {
             let set: HashSet<Vec<usize>> = super::permutations(5..10).collect();
             assert_eq!(set.len(), 120);
         }
+
+        #[test]
+        fn test_empty_and_singleton() {
+            assert_eq!(super::get_permutation(0, 0), Vec::<usize>::new());
+            assert_eq!(super::get_permutation(0, 1), vec![0]);
+
+            let mut empty = super::permutations(0..0);
+            assert_eq!(empty.next(), Some(vec![]));
+            assert_eq!(empty.next(), None);
+            assert_eq!(empty.next(), None);
+
+            let mut single = super::permutations(0..1);
+            assert_eq!(single.next(), Some(vec![0]));
+            assert_eq!(single.next(), None);
+            assert_eq!(single.next(), None);
+        }
+
+        #[test]
+        fn test_heap_matches_permutations() {
+            use std::collections::HashSet;
+
+            for n in 0..7 {
+                let heap: Vec<Vec<usize>> = super::heap_permutations(0..n).collect();
+                let expected: HashSet<Vec<usize>> = super::permutations(0..n).collect();
+
+                let heap_set: HashSet<Vec<usize>> = heap.iter().cloned().collect();
+                assert_eq!(heap.len(), expected.len());
+                assert_eq!(heap_set, expected);
+            }
+        }
+
+        #[test]
+        fn test_heap_next_ref() {
+            let mut perms = super::heap_permutations(vec!['a', 'b', 'c'].into_iter());
+            let mut count = 0;
+            while let Some(p) = perms.next_ref() {
+                assert_eq!(p.len(), 3);
+                count += 1;
+            }
+            assert_eq!(count, 6);
+            assert_eq!(perms.next_ref(), None);
+        }
+    }
+}
+
+pub mod cycle {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    /// A cycle in a sequence of states.
+    #[allow(dead_code)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Cycle {
+        /// Step at which the repeated state was first seen.
+        pub start: u64,
+        /// Number of steps between repeats of the state.
+        pub length: u64,
+    }
+
+    /// Repeatedly apply `step` to `initial` until a state repeats.
+    ///
+    /// This remembers every state seen so far, so it works for any
+    /// deterministic system (e.g. a general cellular automaton), at the cost of
+    /// memory proportional to the number of steps before the first repeat.
+    #[allow(dead_code)]
+    pub fn find_cycle<S, F>(initial: S, mut step: F) -> Cycle
+    where
+        S: Eq + Hash,
+        F: FnMut(&S) -> S,
+    {
+        let mut seen: HashMap<S, u64> = HashMap::new();
+        let mut state = initial;
+        let mut count = 0;
+
+        loop {
+            let next = step(&state);
+            if let Some(start) = seen.insert(state, count) {
+                return Cycle {
+                    start,
+                    length: count - start,
+                };
+            }
+            state = next;
+            count += 1;
+        }
+    }
+
+    /// Count the number of applications of `step` needed to return to
+    /// `initial`.
+    ///
+    /// Precondition: `step` must be reversible (i.e., every state has exactly
+    /// one predecessor). For reversible systems, the first repeated state is
+    /// always the initial state, so we only need to compare against the
+    /// starting point instead of storing every state we've seen. If `step`
+    /// isn't reversible, this may never terminate.
+    pub fn find_return_to_start<S, F>(initial: &S, mut step: F) -> u64
+    where
+        S: Eq,
+        F: FnMut(&S) -> S,
+    {
+        let mut state = step(initial);
+        let mut count = 1;
+
+        while state != *initial {
+            state = step(&state);
+            count += 1;
+        }
+
+        count
+    }
+
+    mod test {
+        #[test]
+        fn test_find_cycle_with_prefix() {
+            // 0 -> 1 -> 2 -> 3 -> 4 -> 2 -> ...
+            let cycle = super::find_cycle(0, |&x| if x == 4 { 2 } else { x + 1 });
+            assert_eq!(
+                cycle,
+                super::Cycle {
+                    start: 2,
+                    length: 3
+                }
+            );
+        }
+
+        #[test]
+        fn test_find_return_to_start_agrees() {
+            let step = |&x: &u64| (x + 3) % 10;
+
+            let cycle = super::find_cycle(4, step);
+            assert_eq!(
+                cycle,
+                super::Cycle {
+                    start: 0,
+                    length: 10
+                }
+            );
+            assert_eq!(super::find_return_to_start(&4, step), cycle.length);
+        }
+    }
+}
+
+pub mod math {
+    use std::cmp::Ordering;
+    use std::ops::Rem;
+
+    /// Sum of the absolute values of three numbers.
+    pub fn abs_sum3(x: i64, y: i64, z: i64) -> i64 {
+        x.abs() + y.abs() + z.abs()
+    }
+
+    /// Divide `n` by `d`, rounding up.
+    pub fn ceil_div(n: u64, d: u64) -> u64 {
+        n.div_ceil(d)
+    }
+
+    /// -1, 0, or 1, depending on the sign of `x`.
+    pub fn signum(x: i64) -> i64 {
+        match x.cmp(&0) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    }
+
+    /// Greatest common divisor of `n` and `m`, via Euclid's algorithm.
+    ///
+    /// `gcd(n, 0)` is `n`.
+    pub fn gcd<T>(mut n: T, mut m: T) -> T
+    where
+        T: Copy + Default + PartialEq + Rem<Output = T>,
+    {
+        let zero = T::default();
+        while m != zero {
+            let r = n % m;
+            n = m;
+            m = r;
+        }
+        n
+    }
+
+    /// Least common multiple of `n` and `m`.
+    ///
+    /// We divide by the gcd before multiplying, so this only overflows if the
+    /// result itself isn't representable, in which case it panics.
+    pub fn lcm(n: u64, m: u64) -> u64 {
+        if n == 0 || m == 0 {
+            return 0;
+        }
+
+        let g = gcd(n, m);
+        (n / g).checked_mul(m).expect("lcm overflowed")
+    }
+
+    mod test {
+        #[test]
+        fn test_abs_sum3() {
+            assert_eq!(super::abs_sum3(0, 0, 0), 0);
+            assert_eq!(super::abs_sum3(1, -2, 3), 6);
+            assert_eq!(super::abs_sum3(-4, -5, -6), 15);
+        }
+
+        #[test]
+        fn test_ceil_div() {
+            assert_eq!(super::ceil_div(0, 3), 0);
+            assert_eq!(super::ceil_div(1, 3), 1);
+            assert_eq!(super::ceil_div(3, 3), 1);
+            assert_eq!(super::ceil_div(7, 3), 3);
+            assert_eq!(super::ceil_div(10, 1), 10);
+        }
+
+        #[test]
+        fn test_signum() {
+            assert_eq!(super::signum(-17), -1);
+            assert_eq!(super::signum(0), 0);
+            assert_eq!(super::signum(42), 1);
+        }
+
+        #[test]
+        fn test_gcd() {
+            use super::gcd;
+
+            assert_eq!(gcd(1, 1), 1);
+            assert_eq!(gcd(2, 2), 2);
+            assert_eq!(gcd(100, 100), 100);
+
+            assert_eq!(gcd(2, 4), 2);
+            assert_eq!(gcd(4, 2), 2);
+
+            assert_eq!(gcd(3, 6), 3);
+            assert_eq!(gcd(6, 3), 3);
+
+            assert_eq!(gcd(24, 18), 6);
+            assert_eq!(gcd(18, 24), 6);
+
+            assert_eq!(gcd(0usize, 5), 5);
+            assert_eq!(gcd(5usize, 0), 5);
+        }
+
+        #[test]
+        fn test_lcm() {
+            use super::lcm;
+
+            assert_eq!(lcm(4, 6), 12);
+            assert_eq!(lcm(7, 7), 7);
+            assert_eq!(lcm(1, 9), 9);
+
+            // Periods of a realistic size for day 12 axes.
+            let periods: [u64; 3] = [186028, 231614, 108344];
+            let expected = 583_523_031_727_256u64;
+            assert_eq!(lcm(lcm(periods[0], periods[1]), periods[2]), expected);
+        }
+
+        #[test]
+        fn test_lcm_large_periods() {
+            use super::lcm;
+
+            // Both periods are larger than u32::MAX, so their product overflows
+            // a u64, but their lcm doesn't.
+            let n: u64 = 6_000_000_000;
+            let m: u64 = 9_000_000_000;
+            assert!(n.checked_mul(m).is_none());
+            assert_eq!(lcm(n, m), 18_000_000_000);
+
+            assert_eq!(lcm(0u64, m), 0);
+        }
+
+        #[test]
+        #[should_panic(expected = "lcm overflowed")]
+        fn test_lcm_overflow() {
+            // Consecutive numbers are coprime, so this is their product.
+            super::lcm(u64::MAX - 1, u64::MAX - 2);
+        }
     }
 }
 
@@ -160,3 +591,177 @@ where
         }
     }
 }
+
+/// A memoization table for recursive computations.
+///
+/// The table is stored in a RefCell so that it can be updated through a
+/// shared reference, which allows memoized functions to recurse through
+/// `&self` methods.
+#[derive(Debug)]
+pub struct Memo<K, V> {
+    table: RefCell<HashMap<K, V>>,
+}
+
+impl<K: Hash + Eq, V: Clone> Memo<K, V> {
+    pub fn new() -> Memo<K, V> {
+        Memo {
+            table: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Create an empty table with room for at least `capacity` entries.
+    #[allow(dead_code)]
+    pub fn with_capacity(capacity: usize) -> Memo<K, V> {
+        Memo {
+            table: RefCell::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    /// Make room for at least `additional` more entries.
+    pub fn reserve(&self, additional: usize) {
+        self.table.borrow_mut().reserve(additional);
+    }
+
+    /// Forget all memoized values.
+    pub fn clear(&self) {
+        self.table.borrow_mut().clear();
+    }
+
+    /// Number of memoized values.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.table.borrow().len()
+    }
+
+    /// Get the memoized value for `key`, if any.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.table.borrow().get(key).cloned()
+    }
+
+    /// Record the value for `key`.
+    pub fn insert(&self, key: K, value: V) {
+        self.table.borrow_mut().insert(key, value);
+    }
+
+    /// Get the memoized value for `key`, computing it with `f` if it hasn't
+    /// been computed yet.
+    ///
+    /// The table is not borrowed while `f` runs, so `f` may recursively look
+    /// up other keys in this table.
+    pub fn get_or_compute<F: FnOnce() -> V>(&self, key: K, f: F) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+
+        let value = f();
+        self.insert(key, value.clone());
+        value
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for Memo<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Memo {
+            table: RefCell::new(iter.into_iter().collect()),
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_parse_part() {
+        use super::Part;
+
+        assert_eq!("1".parse::<Part>().unwrap(), Part::One);
+        assert_eq!("2".parse::<Part>().unwrap(), Part::Two);
+        assert!("3".parse::<Part>().is_err());
+
+        assert!(Part::Both.includes(Part::One));
+        assert!(Part::Both.includes(Part::Two));
+        assert!(!Part::One.includes(Part::Two));
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        use super::parse_lines;
+
+        let parsed: Vec<u64> = parse_lines("12\n14\n1969\n").unwrap();
+        assert_eq!(parsed, vec![12, 14, 1969]);
+
+        let empty: Vec<u64> = parse_lines("").unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_parse_lines_error() {
+        use super::parse_lines;
+
+        let err = parse_lines::<u64>("12\nfourteen\n1969").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: invalid digit found in string (\"fourteen\")"
+        );
+
+        let err = parse_lines::<u64>("x\n1\n-2").unwrap_err();
+        assert_eq!(err.to_string().lines().count(), 2);
+        assert!(err.to_string().starts_with("line 1: "));
+        assert!(err.to_string().contains("line 3: "));
+    }
+
+    #[test]
+    fn test_display_answers() {
+        use super::ProblemAnswers;
+
+        let mut answers = ProblemAnswers::default();
+        assert_eq!(answers.to_string(), "");
+
+        answers.part2 = Some("8444".into());
+        assert_eq!(answers.to_string(), "Part 2: 8444");
+
+        answers.part1 = Some("3101878".into());
+        assert_eq!(answers.to_string(), "Part 1: 3101878\nPart 2: 8444");
+    }
+
+    #[test]
+    fn test_memo_computes_once() {
+        use super::Memo;
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let memo: Memo<u64, u64> = Memo::new();
+        let square = |n: u64| {
+            memo.get_or_compute(n, || {
+                calls.set(calls.get() + 1);
+                n * n
+            })
+        };
+
+        assert_eq!(square(3), 9);
+        assert_eq!(square(3), 9);
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(square(4), 16);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_memo_recursive() {
+        use super::Memo;
+
+        fn fib(memo: &Memo<u64, u64>, n: u64) -> u64 {
+            memo.get_or_compute(n, || match n {
+                0 | 1 => n,
+                _ => fib(memo, n - 1) + fib(memo, n - 2),
+            })
+        }
+
+        let memo = Memo::new();
+        assert_eq!(fib(&memo, 80), 23416728348467685);
+        assert_eq!(memo.len(), 81);
+
+        memo.clear();
+        assert_eq!(memo.len(), 0);
+        assert_eq!(memo.get(&80), None);
+        assert_eq!(fib(&memo, 10), 55);
+    }
+}
@@ -0,0 +1,192 @@
+//! Check each day's answers against known-good values.
+//!
+//! Most solvers only print their answers, so these tests run the binary on
+//! the committed puzzle inputs and look for the expected lines in its output.
+//! Days without a committed input aren't checked.
+
+use std::process::Command;
+
+/// Run `aoc2019 <args>` and return its output, failing if it exits with an
+/// error.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_aoc2019"))
+        .args(args)
+        .output()
+        .expect("failed to start aoc2019");
+
+    assert!(
+        output.status.success(),
+        "aoc2019 {:?} failed:\n{}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Check that every line in `expected` appears in the output of
+/// `aoc2019 <args>`. Surrounding whitespace is ignored.
+fn check(args: &[&str], expected: &[&str]) {
+    let output = run(args);
+    let lines: Vec<&str> = output.lines().map(str::trim).collect();
+    for line in expected {
+        assert!(
+            lines.contains(line),
+            "aoc2019 {:?}: expected line {:?} in output:\n{}",
+            args,
+            line,
+            output
+        );
+    }
+}
+
+#[test]
+fn problem1() {
+    check(&["1"], &["Part 1: 3328306", "Part 2: 4989588"]);
+}
+
+#[test]
+fn problem2() {
+    check(&["2"], &["Part 1: 3101878", "Part 2: 8444"]);
+}
+
+#[test]
+fn problem3() {
+    check(&["3"], &["Distance is 806.", "Delay is 66076"]);
+}
+
+#[test]
+fn problem4() {
+    check(
+        &["4"],
+        &[
+            "Num Valid Passwords (Part 1): 945",
+            "Num Valid Passwords (Part 2): 617",
+        ],
+    );
+}
+
+#[test]
+fn problem5() {
+    check(
+        &["5"],
+        &["[0, 0, 0, 0, 0, 0, 0, 0, 0, 5044655]", "[7408802]"],
+    );
+}
+
+#[test]
+fn problem6() {
+    check(
+        &["6"],
+        &[
+            "Total Number of Orbits: 142497",
+            "Number of Orbital Transitions: 301",
+        ],
+    );
+}
+
+#[test]
+fn problem7() {
+    check(
+        &["7"],
+        &[
+            "Max thrust (Part 1): 298586",
+            "Max thrust (Part 2): 9246095",
+        ],
+    );
+}
+
+#[test]
+fn problem8() {
+    check(
+        &["8"],
+        &[
+            "2016",
+            "░██░█░░░░██░░██░░░░█░██░█",
+            "░██░████░█░██░████░█░██░█",
+            "░░░░███░██░██████░██░██░█",
+            "░██░██░███░█████░███░██░█",
+            "░██░█░████░██░█░████░██░█",
+            "░██░█░░░░██░░██░░░░██░░██",
+        ],
+    );
+}
+
+#[test]
+fn problem9() {
+    check(&["9"], &["[3497884671]", "[46470]"]);
+}
+
+#[test]
+fn problem10() {
+    check(
+        &["10"],
+        &["Max visibility: 344 at (30, 34)", "Answer: 2732"],
+    );
+}
+
+#[test]
+fn problem11() {
+    check(
+        &["11"],
+        &[
+            "Number of painted locations: 2293",
+            "██░░██░██░█░█████░░██░░░██░░░███░░██░███████",
+            "█░██░█░██░█░████░██░█░██░█░██░█░██░█░███████",
+            "█░██░█░░░░█░████░████░██░█░██░█░██░█░███████",
+            "█░░░░█░██░█░████░████░░░██░░░██░░░░█░███████",
+            "█░██░█░██░█░████░██░█░████░█░██░██░█░███████",
+            "█░██░█░██░█░░░░██░░██░████░██░█░██░█░░░░████",
+        ],
+    );
+}
+
+#[test]
+fn problem12() {
+    check(
+        &["12"],
+        &[
+            "Total Energy: 8044",
+            "State repeats after 362375881472136 steps.",
+        ],
+    );
+}
+
+#[test]
+fn problem13() {
+    check(
+        &["13"],
+        &["Number of block tiles: 412", "Final Score: 20940"],
+    );
+}
+
+#[test]
+fn problem14() {
+    check(
+        &["14"],
+        &[
+            "ORE needed to produce 1 FUEL: 337862",
+            "Max fuel produced with 1000000000000 ORE: 3687786",
+        ],
+    );
+}
+
+#[test]
+fn problem15() {
+    check(
+        &["15"],
+        &["Steps from entrance: 234", "Max distance from oxygen: 292"],
+    );
+}
+
+#[test]
+fn problem16() {
+    check(
+        &["16"],
+        &["First 8 digits: 25131128", "Secret Message: 53201602"],
+    );
+}
+
+#[test]
+fn problem17() {
+    check(&["17"], &["Total: 7720", "Dust collected: 1681189"]);
+}